@@ -1,99 +1,283 @@
 use std::path::PathBuf;
 
-#[derive(Debug)]
-pub enum Semver {
-    Major,
-    Minor,
-    Patch,
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::constants::{DEFAULT_CACHE_TTL_MINUTES, DEFAULT_CONCURRENCY, EXIT_CODES};
+use crate::report::Bump;
+use crate::target::Target;
+
+// How version ranges are written back when a package is updated
+#[derive(PartialEq, Debug, Clone, Copy, ValueEnum)]
+pub enum RangePolicy {
+    Preserve,
+    Pin,
+    Caret,
+    Tilde,
+}
+
+// How results are printed
+#[derive(PartialEq, Debug, Clone, Copy, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
 }
 
-#[derive(PartialEq, Debug)]
+// When the package manager's install runs after updating
+#[derive(PartialEq, Debug, Clone, Copy, ValueEnum)]
+pub enum Install {
+    Always,
+    Never,
+    Prompt,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, ValueEnum)]
 pub enum Include {
     Dev,
     Peer,
 }
 
-#[derive(PartialEq, Debug)]
+// Upper bound of --semver, kept next to --target since npm-check-updates users know both
+#[derive(PartialEq, Debug, Clone, Copy, ValueEnum)]
+enum Semver {
+    Major,
+    Minor,
+    Patch,
+}
+
+#[derive(PartialEq, Debug, Subcommand)]
 pub enum Command {
-    Help,
+    /// List outdated packages without prompting or writing anything
+    Outdated,
+    /// Update every outdated package, same as -u
+    Update,
+    /// List outdated packages and exit with a non-zero code if there are any, for CI
+    Check,
+    /// Manage the registry metadata cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Print author and source code location
     About,
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Delete the registry metadata cache
+    Clean,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "nrush", version, about, after_help = EXIT_CODES)]
 pub struct Arguments {
+    #[command(subcommand)]
     pub command: Option<Command>,
+
+    /// Update all packages
+    #[arg(short, long, global = true)]
     pub update: bool,
+
+    /// Pick the packages to update
+    #[arg(short, long, global = true)]
     pub interactive: bool,
+
+    /// Check every workspace member along with the root package.json
+    #[arg(short, long, global = true)]
+    pub workspaces: bool,
+
+    /// Run the package manager's install after updating, --install alone means always
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        ignore_case = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always",
+        value_name = "WHEN"
+    )]
+    pub install: Option<Install>,
+
+    /// Align dependencies declared with different ranges across workspace members, implies -w
+    #[arg(long, global = true)]
+    pub consistent: bool,
+
+    /// Path to package.json or the directory containing it
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value = "",
+        hide_default_value = true,
+        value_parser = existing_path
+    )]
     pub path: PathBuf,
-    pub semver: Option<Semver>,
+
+    /// Update up to the specified semver type, same as --target latest, minor or patch
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_enum,
+        ignore_case = true,
+        conflicts_with = "target"
+    )]
+    semver: Option<Semver>,
+
+    /// Version to upgrade to
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_enum,
+        ignore_case = true,
+        default_value = "latest"
+    )]
+    pub target: Target,
+
+    /// Include dev and/or peer dependencies, eg. --include dev,peer
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        ignore_case = true
+    )]
     pub include: Option<Vec<Include>>,
-    pub skip_ranges: bool,
+
+    /// How ranges are written back
+    #[arg(
+        long = "range",
+        global = true,
+        value_enum,
+        ignore_case = true,
+        default_value = "preserve",
+        value_name = "RANGE"
+    )]
+    pub range_policy: RangePolicy,
+
+    /// Pin updated packages to the exact version, same as --range pin
+    #[arg(
+        long,
+        global = true,
+        visible_alias = "skip-ranges",
+        conflicts_with = "range_policy"
+    )]
+    pin: bool,
+
+    /// Update "*" versions too
+    #[arg(long, global = true)]
     pub update_any: bool,
+
+    /// Registry URL, overrides .npmrc and NPM_CONFIG_REGISTRY
+    #[arg(long, global = true)]
+    pub registry: Option<String>,
+
+    /// Maximum number of registry requests in flight
+    #[arg(long, global = true, default_value_t = DEFAULT_CONCURRENCY, value_parser = concurrency)]
+    pub concurrency: usize,
+
+    /// Don't read or write the registry metadata cache
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Minutes a cached packument is used without revalidating
+    #[arg(long, global = true, default_value_t = DEFAULT_CACHE_TTL_MINUTES)]
+    pub cache_ttl: u64,
+
+    /// Only use cached registry metadata, never touch the network
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Compare against the installed version from the lockfile instead of the declared range
+    #[arg(long, global = true)]
+    pub installed: bool,
+
+    /// Dist-tag to target (eg. next, beta, canary), falls back to latest
+    #[arg(long, global = true)]
+    pub tag: Option<String>,
+
+    /// Allow updating stable versions to prereleases
+    #[arg(long, global = true)]
+    pub pre: bool,
+
+    /// Days a version has to be out before it's offered
+    #[arg(long, global = true, value_name = "DAYS")]
+    pub cooldown: Option<u64>,
+
+    /// Output format, JSON never clears the screen or prompts
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        ignore_case = true,
+        default_value = "text"
+    )]
+    pub format: Format,
+
+    /// Only report, never prompt or write, and exit with 1 if anything is outdated
+    #[arg(long, global = true)]
+    pub check: bool,
+
+    /// Smallest update that fails --check (patch, minor, major)
+    #[arg(long, global = true, value_parser = error_level, value_name = "LEVEL")]
+    pub error_level: Option<Bump>,
 }
 
 impl Arguments {
+    // Function to parse the command line. Invalid arguments print an error and exit,
+    // and the subcommands are turned into the flags they stand for
     pub fn new() -> Arguments {
-        let mut args = Arguments {
-            command: None,
-            update: false,
-            interactive: false,
-            path: PathBuf::new(),
-            semver: None,
-            include: None,
-            skip_ranges: false,
-            update_any: false,
-        };
-
-        let mut args_iter = std::env::args().skip(1);
-
-        while let Some(arg) = args_iter.next() {
-            match arg.to_lowercase().as_str() {
-                "-u" | "--update" => args.update = true,
-                "-i" | "--interactive" => args.interactive = true,
-                "-p" | "--path" => {
-                    if let Some(path) = args_iter.next() {
-                        args.path = PathBuf::from(path);
-                        if !args.path.exists() {
-                            panic!("Path does not exist");
-                        }
-                    }
-                }
-                "-s" | "--semver" => {
-                    if let Some(semver) = args_iter.next() {
-                        args.semver = match semver.to_lowercase().as_str() {
-                            "major" => Some(Semver::Major),
-                            "minor" => Some(Semver::Minor),
-                            "patch" => Some(Semver::Patch),
-                            _ => panic!("Invalid semver type. Must be major, minor, or patch"),
-                        }
-                    }
-                }
-                "--include" => {
-                    if let Some(include) = args_iter.next() {
-                        args.include = Some(
-                            include
-                                .split(",")
-                                .map(|s| match s {
-                                    "dev" => Include::Dev,
-                                    "peer" => Include::Peer,
-                                    _ => panic!("Invalid include type"),
-                                })
-                                .collect(),
-                        );
-                    }
-                }
-                "--skip-ranges" => args.skip_ranges = true,
-                "--update-any" => args.update_any = true,
-                _ => {
-                    args.command = match arg.to_lowercase().as_str() {
-                        "help" => Some(Command::Help),
-                        "about" => Some(Command::About),
-                        _ => panic!("Invalid command"),
-                    }
-                }
-            }
+        let mut args = Arguments::parse();
+
+        match args.semver {
+            Some(Semver::Major) => args.target = Target::Latest,
+            Some(Semver::Minor) => args.target = Target::Minor,
+            Some(Semver::Patch) => args.target = Target::Patch,
+            None => (),
+        }
+        if args.consistent {
+            args.workspaces = true;
+        }
+        if args.pin {
+            args.range_policy = RangePolicy::Pin;
+        }
+        match args.command {
+            Some(Command::Update) => args.update = true,
+            Some(Command::Check) => args.check = true,
+            _ => (),
         }
 
         args
     }
+
+    // Whether only the list of outdated packages should be shown, without a prompt
+    pub fn list_only(&self) -> bool {
+        self.command == Some(Command::Outdated)
+    }
+}
+
+fn existing_path(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if path.as_os_str().is_empty() || path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("{} does not exist", path.display()))
+    }
+}
+
+fn concurrency(concurrency: &str) -> Result<usize, String> {
+    match concurrency.parse::<usize>() {
+        Ok(concurrency) if concurrency > 0 => Ok(concurrency),
+        _ => Err("must be a number above 0".to_string()),
+    }
+}
+
+fn error_level(level: &str) -> Result<Bump, String> {
+    match level.to_lowercase().as_str() {
+        "patch" => Ok(Bump::Patch),
+        "minor" => Ok(Bump::Minor),
+        "major" => Ok(Bump::Major),
+        _ => Err("must be patch, minor, or major".to_string()),
+    }
 }
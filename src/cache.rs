@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+// A cached registry response, with what's needed to revalidate it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // Seconds since the epoch when the registry last confirmed this body
+    pub fetched_at: u64,
+    pub body: String,
+}
+
+impl CacheEntry {
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+}
+
+// On disk packument cache, $XDG_CACHE_HOME/nrush by default
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Cache {
+        Cache {
+            dir: cache_dir(),
+            ttl,
+        }
+    }
+
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        entry.age() < self.ttl
+    }
+
+    // Function to read an entry, falling back to npm's own cache if nrush has none
+    pub fn read(&self, url: &str, full: bool) -> Option<CacheEntry> {
+        fs::read_to_string(self.entry_path(url, full))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .or_else(|| if full { None } else { read_npm_cache(url) })
+    }
+
+    // Function to write an entry, going through a temporary file so that
+    // concurrent runs never see a half written entry
+    pub fn write(&self, full: bool, entry: &CacheEntry) {
+        let path = self.entry_path(&entry.url, full);
+        let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temporary, serde_json::to_vec(entry).unwrap()))
+            .and_then(|_| fs::rename(&temporary, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+
+    // Function to make the file name for a URL, eg. registry.npmjs.org_left-pad.abbreviated.json
+    fn entry_path(&self, url: &str, full: bool) -> PathBuf {
+        let variant = if full { "full" } else { "abbreviated" };
+        let name = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '@') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        self.dir.join(format!("{}.{}.json", name, variant))
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// Function to find the cache directory: $NRUSH_CACHE_DIR, $XDG_CACHE_HOME/nrush,
+// %LOCALAPPDATA%\nrush on Windows, or ~/.cache/nrush
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("NRUSH_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        return Path::new(&dir).join("nrush");
+    }
+    if let Some(dir) = env::var_os("LOCALAPPDATA") {
+        return Path::new(&dir).join("nrush");
+    }
+    let home = env::var_os("HOME").unwrap_or_default();
+    Path::new(&home).join(".cache").join("nrush")
+}
+
+// Function to delete every cached entry, used by `nrush cache clean`. Only the files
+// nrush writes are removed, since $NRUSH_CACHE_DIR could point at a directory shared
+// with other things, and the directory itself only goes if that leaves it empty
+pub fn clean() -> io::Result<PathBuf> {
+    let dir = cache_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(dir),
+        Err(error) => return Err(error),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let is_cache_entry = name.ends_with(".abbreviated.json") || name.ends_with(".full.json");
+        if is_cache_entry && entry.file_type()?.is_file() {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    if fs::read_dir(&dir)?.next().is_none() {
+        fs::remove_dir(&dir)?;
+    }
+    Ok(dir)
+}
+
+// Function to find npm's cache directory: $npm_config_cache, %LOCALAPPDATA%\npm-cache or ~/.npm
+fn npm_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("npm_config_cache").or_else(|| env::var_os("NPM_CONFIG_CACHE")) {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        return env::var_os("LOCALAPPDATA").map(|dir| Path::new(&dir).join("npm-cache"));
+    }
+    env::var_os("HOME").map(|home| Path::new(&home).join(".npm"))
+}
+
+// Function to read a packument from npm's _cacache, which npm fills on every install.
+// The index is bucketed by the sha256 of the request key, and the content is stored
+// by its sha512 integrity. npm always asks for abbreviated metadata, so that's all it can answer
+fn read_npm_cache(url: &str) -> Option<CacheEntry> {
+    let cacache = npm_cache_dir()?.join("_cacache");
+    let key = format!("make-fetch-happen:request-cache:{}", url);
+    let bucket = hex(&Sha256::digest(key.as_bytes()));
+    let index = fs::read_to_string(
+        cacache
+            .join("index-v5")
+            .join(&bucket[0..2])
+            .join(&bucket[2..4])
+            .join(&bucket[4..]),
+    )
+    .ok()?;
+
+    // Every line is "<sha1>\t<json>", and the last matching line wins
+    let entry = index
+        .lines()
+        .rev()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(_, json)| serde_json::from_str::<Value>(json).ok())
+        .find(|entry| entry["key"] == key.as_str())?;
+
+    let integrity = entry["integrity"].as_str()?;
+    let digest = STANDARD.decode(integrity.strip_prefix("sha512-")?).ok()?;
+    let digest = hex(&digest);
+    let body = fs::read_to_string(
+        cacache
+            .join("content-v2")
+            .join("sha512")
+            .join(&digest[0..2])
+            .join(&digest[2..4])
+            .join(&digest[4..]),
+    )
+    .ok()?;
+
+    let headers = &entry["metadata"]["resHeaders"];
+    Some(CacheEntry {
+        url: url.to_string(),
+        etag: headers["etag"].as_str().map(|etag| etag.to_string()),
+        last_modified: headers["last-modified"]
+            .as_str()
+            .map(|date| date.to_string()),
+        fetched_at: entry["time"].as_u64().map_or(0, |time| time / 1000),
+        body,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
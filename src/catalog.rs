@@ -0,0 +1,309 @@
+use std::ops::Range;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::manifest::{Manifest, MANIFEST_FILE};
+use crate::workspace::{split_key, strip_comment, unquote, PNPM_WORKSPACE_FILE};
+
+// A set of versions workspace members refer to with catalog: specifiers
+pub struct Catalog {
+    // "default" for the unnamed catalog
+    pub name: String,
+    // Index of the manifest declaring it
+    pub manifest: usize,
+    // Keys of the object holding its versions, eg. ["workspaces", "catalog"]
+    pub section: Vec<String>,
+}
+
+impl Catalog {
+    // Name shown above the catalog's results, eg. "catalog" or "catalog:react17"
+    pub fn label(&self) -> String {
+        if self.name == "default" {
+            "catalog".to_string()
+        } else {
+            format!("catalog:{}", self.name)
+        }
+    }
+
+    pub fn entries<'a>(&self, manifests: &'a [Manifest]) -> Option<&'a Map<String, Value>> {
+        self.section
+            .iter()
+            .try_fold(&manifests[self.manifest].json, |value, key| value.get(key))?
+            .as_object()
+    }
+}
+
+// Function to find the catalogs of a workspace. bun declares them in the root package.json,
+// at the top level or under `workspaces`, and pnpm in pnpm-workspace.yaml. Files that
+// aren't being checked already, like the root package.json when checking a member, are
+// added to the manifests so their catalogs are written back like any other file
+pub fn load_catalogs(root_dir: &Path, manifests: &mut Vec<Manifest>) -> Vec<Catalog> {
+    let mut catalogs = vec![];
+    let root_manifest = root_dir.join(MANIFEST_FILE);
+    let root = manifests.iter().position(|manifest| {
+        manifest
+            .path
+            .canonicalize()
+            .is_ok_and(|path| path == root_manifest)
+    });
+    match root {
+        Some(root) => {
+            declared_catalogs(&manifests[root].json, root, &[], &mut catalogs);
+            declared_catalogs(&manifests[root].json, root, &["workspaces"], &mut catalogs);
+        }
+        None => {
+            if let Ok(manifest) = Manifest::load(&root_manifest) {
+                declared_catalogs(&manifest.json, manifests.len(), &[], &mut catalogs);
+                declared_catalogs(
+                    &manifest.json,
+                    manifests.len(),
+                    &["workspaces"],
+                    &mut catalogs,
+                );
+                if !catalogs.is_empty() {
+                    manifests.push(manifest);
+                }
+            }
+        }
+    }
+
+    let path = root_dir.join(PNPM_WORKSPACE_FILE);
+    if path.is_file() {
+        match Manifest::load_pnpm_workspace(&path) {
+            Ok(manifest) => {
+                declared_catalogs(&manifest.json, manifests.len(), &[], &mut catalogs);
+                manifests.push(manifest);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    catalogs
+}
+
+fn declared_catalogs(json: &Value, manifest: usize, prefix: &[&str], catalogs: &mut Vec<Catalog>) {
+    let parent = prefix.iter().fold(json, |value, key| &value[key]);
+    let section = |keys: &[&str]| {
+        prefix
+            .iter()
+            .chain(keys)
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    if parent["catalog"].is_object() {
+        catalogs.push(Catalog {
+            name: "default".to_string(),
+            manifest,
+            section: section(&["catalog"]),
+        });
+    }
+    for (name, catalog) in parent["catalogs"].as_object().into_iter().flatten() {
+        if catalog.is_object() {
+            catalogs.push(Catalog {
+                name: name.clone(),
+                manifest,
+                section: section(&["catalogs", name]),
+            });
+        }
+    }
+}
+
+// Function to get the catalog a specifier refers to, eg. "react17" for "catalog:react17".
+// A bare "catalog:" refers to the default catalog
+pub fn catalog_reference(spec: &str) -> Option<&str> {
+    let name = spec.trim().strip_prefix("catalog:")?.trim();
+    Some(if name.is_empty() { "default" } else { name })
+}
+
+// A version in pnpm-workspace.yaml, with where its value sits in the file
+struct CatalogLine {
+    section: Vec<String>,
+    package: String,
+    value: Range<usize>,
+}
+
+// Function to read `catalog` and `catalogs` of pnpm-workspace.yaml into the shape bun
+// uses in package.json, eg. {"catalog": {"react": "^18.2.0"}}
+pub fn read_pnpm_catalogs(content: &str) -> Value {
+    let mut catalogs = Value::Object(Map::new());
+    for line in catalog_lines(content) {
+        let section = line
+            .section
+            .iter()
+            .fold(&mut catalogs, |value, key| &mut value[key.as_str()]);
+        section[line.package.as_str()] = Value::String(unquote(&content[line.value]));
+    }
+    catalogs
+}
+
+// Function to write changed catalog versions back into pnpm-workspace.yaml, only
+// replacing their values so comments and formatting stay as they were
+pub fn patch_pnpm_catalogs(original: &str, updated: &Value) -> String {
+    let mut patched = original.to_string();
+    for line in catalog_lines(original).into_iter().rev() {
+        let section = line.section.iter().fold(updated, |value, key| &value[key]);
+        let Some(version) = section[line.package.as_str()].as_str() else {
+            continue;
+        };
+        let current = &original[line.value.clone()];
+        if unquote(current) != version {
+            let quote = current.chars().next().filter(|c| ['\'', '"'].contains(c));
+            patched.replace_range(line.value, &yaml_scalar(version, quote));
+        }
+    }
+    patched
+}
+
+// Function to find the versions listed under `catalog:` and under each name in `catalogs:`.
+// Only the block style pnpm writes is understood, not inline {a: b} maps
+fn catalog_lines(content: &str) -> Vec<CatalogLine> {
+    let mut lines = vec![];
+    let mut section: Vec<String> = vec![];
+    let mut name_indent = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let text = strip_comment(line.trim_end_matches(['\n', '\r']));
+        let trimmed = text.trim_start();
+        let indent = text.len() - trimmed.len();
+        let Some((key, value)) = split_key(trimmed) else {
+            continue;
+        };
+        let value = start + indent + value.start..start + indent + value.end;
+
+        if indent == 0 {
+            section = match key.as_str() {
+                "catalog" | "catalogs" => vec![key],
+                _ => vec![],
+            };
+            name_indent = None;
+            continue;
+        }
+
+        match section.first().map(|key| key.as_str()) {
+            Some("catalog") if !value.is_empty() => lines.push(CatalogLine {
+                section: section.clone(),
+                package: key,
+                value,
+            }),
+            Some("catalogs") if name_indent.is_none_or(|name_indent| indent <= name_indent) => {
+                name_indent = Some(indent);
+                section = vec!["catalogs".to_string(), key];
+            }
+            Some("catalogs") if section.len() == 2 && !value.is_empty() => {
+                lines.push(CatalogLine {
+                    section: section.clone(),
+                    package: key,
+                    value,
+                })
+            }
+            _ => (),
+        }
+    }
+    lines
+}
+
+// Function to write a version as a YAML scalar, quoting it when the original was quoted
+// or when a plain scalar would be read as something else, eg. ">=1.0.0" or "*"
+fn yaml_scalar(version: &str, quote: Option<char>) -> String {
+    let needs_quotes = version.starts_with([
+        '*', '&', '!', '|', '>', '%', '@', '`', '\'', '"', '{', '[', '#', '?', '-', ':', ',',
+    ]) || version.contains(": ")
+        || version.contains(" #");
+    match quote {
+        Some(quote) => format!("{}{}{}", quote, version, quote),
+        None if needs_quotes => format!("'{}'", version),
+        None => version.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const WORKSPACE: &str = "packages:
+  - packages/*
+
+catalog:
+  react: ^18.2.0
+  '@scope/ui': \"~1.0.0\"   # pinned
+  \"vue\": '3.4.0'
+
+catalogs:
+  react17:
+    react: ^17.0.2
+    react-dom: \">=17.0.0\"
+  legacy:
+    lodash: 4.17.20 # old
+overrides:
+  react: 1.0.0
+";
+
+    #[test]
+    fn finds_catalog_versions() {
+        let lines = catalog_lines(WORKSPACE)
+            .into_iter()
+            .map(|line| {
+                (
+                    line.section.join("."),
+                    line.package,
+                    WORKSPACE[line.value].to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            ("catalog", "react", "^18.2.0"),
+            ("catalog", "@scope/ui", "\"~1.0.0\""),
+            ("catalog", "vue", "'3.4.0'"),
+            ("catalogs.react17", "react", "^17.0.2"),
+            ("catalogs.react17", "react-dom", "\">=17.0.0\""),
+            ("catalogs.legacy", "lodash", "4.17.20"),
+        ]
+        .map(|(section, package, value)| {
+            (section.to_string(), package.to_string(), value.to_string())
+        });
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn reads_quoted_scalars() {
+        assert_eq!(
+            read_pnpm_catalogs(WORKSPACE),
+            json!({
+                "catalog": {"react": "^18.2.0", "@scope/ui": "~1.0.0", "vue": "3.4.0"},
+                "catalogs": {
+                    "react17": {"react": "^17.0.2", "react-dom": ">=17.0.0"},
+                    "legacy": {"lodash": "4.17.20"}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn patches_only_changed_values_with_crlf() {
+        let original = WORKSPACE.replace('\n', "\r\n");
+        let mut updated = read_pnpm_catalogs(&original);
+        updated["catalog"]["react"] = json!("^18.3.1");
+        updated["catalog"]["@scope/ui"] = json!("~1.2.0");
+        updated["catalogs"]["react17"]["react-dom"] = json!(">=17.0.2");
+        updated["catalogs"]["legacy"]["lodash"] = json!(">=4.17.21");
+
+        let expected = original
+            .replace("react: ^18.2.0", "react: ^18.3.1")
+            .replace("\"~1.0.0\"", "\"~1.2.0\"")
+            .replace("\">=17.0.0\"", "\">=17.0.2\"")
+            .replace("lodash: 4.17.20 # old", "lodash: '>=4.17.21' # old");
+        assert_eq!(patch_pnpm_catalogs(&original, &updated), expected);
+    }
+
+    #[test]
+    fn leaves_unchanged_catalogs_alone() {
+        let updated = read_pnpm_catalogs(WORKSPACE);
+        assert_eq!(patch_pnpm_catalogs(WORKSPACE, &updated), WORKSPACE);
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::{Client, Error, StatusCode};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+
+use crate::cache::{now, Cache, CacheEntry};
+use crate::constants::{
+    MAX_RETRIES, MAX_RETRY_DELAY_MS, REQUEST_TIMEOUT_SECS, RETRY_BASE_DELAY_MS,
+};
+use crate::npmrc::Npmrc;
+
+// Abbreviated ("corgi") metadata only has what installs need, which is a fraction
+// of the full document for packages with a long history
+const ABBREVIATED_METADATA: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+const FULL_METADATA: &str = "application/json";
+
+// Everything that can go wrong while looking up a package
+#[derive(Debug)]
+pub enum FetchError {
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    // --offline was passed and there's no cached copy
+    NotCached,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Http(error) => write!(f, "{}", error),
+            FetchError::Json(error) => write!(f, "Invalid registry response: {}", error),
+            FetchError::NotCached => write!(f, "No cached data available offline"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(error: reqwest::Error) -> Self {
+        FetchError::Http(error)
+    }
+}
+
+impl From<serde_json::Error> for FetchError {
+    fn from(error: serde_json::Error) -> Self {
+        FetchError::Json(error)
+    }
+}
+
+// A packument body, with its age if it came from the cache without revalidation
+pub struct RegistryResponse {
+    pub body: String,
+    pub cache_age: Option<Duration>,
+}
+
+// One HTTP client shared by every lookup, so connections are reused.
+// Limits how many requests are in flight, retries rate limits and server errors,
+// and keeps packuments in the on disk cache unless --no-cache is passed.
+// With --offline only the cache is used
+pub struct RegistryClient {
+    pub npmrc: Npmrc,
+    client: Client,
+    slots: Semaphore,
+    cache: Option<Cache>,
+    offline: bool,
+}
+
+impl RegistryClient {
+    pub fn new(
+        npmrc: Npmrc,
+        concurrency: usize,
+        cache: Option<Cache>,
+        offline: bool,
+    ) -> RegistryClient {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .user_agent(concat!("nrush/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Unable to create HTTP client");
+
+        RegistryClient {
+            npmrc,
+            client,
+            slots: Semaphore::new(concurrency.max(1)),
+            cache,
+            offline,
+        }
+    }
+
+    // Function to GET a packument, abbreviated unless `full` is set.
+    // Fresh cache entries are used as is, stale ones are revalidated with
+    // If-None-Match / If-Modified-Since and reused on a 304
+    pub async fn get(&self, url: &str, full: bool) -> Result<RegistryResponse, FetchError> {
+        let cached = self.cache.as_ref().and_then(|cache| cache.read(url, full));
+        if let (Some(cache), Some(entry)) = (&self.cache, &cached) {
+            if self.offline || cache.is_fresh(entry) {
+                return Ok(RegistryResponse {
+                    body: entry.body.clone(),
+                    cache_age: Some(entry.age()),
+                });
+            }
+        }
+        if self.offline {
+            return Err(FetchError::NotCached);
+        }
+
+        let mut headers = HeaderMap::new();
+        let accept = if full {
+            FULL_METADATA
+        } else {
+            ABBREVIATED_METADATA
+        };
+        headers.insert(ACCEPT, HeaderValue::from_static(accept));
+        if let Some(entry) = &cached {
+            if let Some(etag) = entry.etag.as_deref().and_then(|etag| etag.parse().ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = entry
+                .last_modified
+                .as_deref()
+                .and_then(|date| date.parse().ok())
+            {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+
+        let (status, response_headers, body) = self.send(url, headers).await?;
+        let header = |name| {
+            response_headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let entry = match cached {
+            Some(mut entry) if status == StatusCode::NOT_MODIFIED => {
+                entry.fetched_at = now();
+                entry.etag = header(ETAG).or(entry.etag);
+                entry
+            }
+            _ => CacheEntry {
+                url: url.to_string(),
+                etag: header(ETAG),
+                last_modified: header(LAST_MODIFIED),
+                fetched_at: now(),
+                body: String::from_utf8_lossy(&body).into_owned(),
+            },
+        };
+
+        if let Some(cache) = &self.cache {
+            cache.write(full, &entry);
+        }
+        Ok(RegistryResponse {
+            body: entry.body,
+            cache_age: None,
+        })
+    }
+
+    // Function to send a request with the matching credentials.
+    // 429 and 5xx responses, timeouts and connection errors are retried with
+    // exponential backoff and jitter, or after the server's Retry-After delay.
+    // The body is read while the request slot is held
+    async fn send(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<(StatusCode, HeaderMap, Vec<u8>), Error> {
+        let mut attempt = 0;
+
+        loop {
+            let delay = {
+                let _slot = self.slots.acquire().await.expect("Request slots closed");
+
+                let mut request = self.client.get(url).headers(headers.clone());
+                if let Some(auth) = self.npmrc.auth_for(url) {
+                    request = request.header(AUTHORIZATION, auth.header_value());
+                }
+
+                match request.send().await {
+                    Ok(response) if is_retryable(response.status()) && attempt < MAX_RETRIES => {
+                        retry_after(response.headers()).unwrap_or_else(|| backoff(attempt))
+                    }
+                    Ok(response) => {
+                        let response = response.error_for_status()?;
+                        let status = response.status();
+                        let headers = response.headers().clone();
+                        let body = response.bytes().await?.to_vec();
+                        return Ok((status, headers, body));
+                    }
+                    Err(error)
+                        if (error.is_timeout() || error.is_connect()) && attempt < MAX_RETRIES =>
+                    {
+                        backoff(attempt)
+                    }
+                    Err(error) => return Err(error),
+                }
+            };
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Function to calculate the delay before the next attempt, doubling every time
+// and adding up to one base delay of random jitter so retries don't line up
+fn backoff(attempt: u32) -> Duration {
+    let exponential = RETRY_BASE_DELAY_MS.saturating_mul(1 << attempt.min(16));
+    let jitter = fastrand::u64(0..=RETRY_BASE_DELAY_MS);
+    Duration::from_millis(exponential.saturating_add(jitter).min(MAX_RETRY_DELAY_MS))
+}
+
+// Function to read Retry-After, which is either a number of seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    };
+    Some(delay.min(Duration::from_millis(MAX_RETRY_DELAY_MS)))
+}
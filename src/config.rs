@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+pub const CONFIG_FILE: &str = ".nrushrc.json";
+
+// Settings read from .nrushrc.json, next to package.json or in the home directory.
+// Command line flags take precedence over anything in here
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    // Dist-tag to target for every package, eg. "next"
+    pub tag: Option<String>,
+    // Dist-tag to target per package, eg. { "react": "next" }
+    pub tags: HashMap<String, String>,
+    // Days a version has to be out before it's offered, eg. 7
+    pub cooldown: Option<u64>,
+}
+
+impl Config {
+    // Function to load the project config, falling back to the one in the home directory.
+    // A config that exists but isn't valid is an error rather than being ignored
+    pub fn load(project_dir: &Path) -> Result<Config, String> {
+        let home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(CONFIG_FILE));
+
+        for file in [Some(project_dir.join(CONFIG_FILE)), home]
+            .into_iter()
+            .flatten()
+        {
+            let Ok(content) = fs::read_to_string(&file) else {
+                continue;
+            };
+            return serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid {}: {}", file.display(), e));
+        }
+        Ok(Config::default())
+    }
+
+    // Function to pick the dist-tag for a package: --tag, then the per-package tag
+    // from the config, then the config's tag, then latest
+    pub fn tag_for<'a>(&'a self, package: &str, cli_tag: Option<&'a str>) -> &'a str {
+        cli_tag
+            .or(self.tags.get(package).map(|tag| tag.as_str()))
+            .or(self.tag.as_deref())
+            .unwrap_or("latest")
+    }
+}
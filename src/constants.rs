@@ -7,20 +7,11 @@ pub const RESET: &str = "\x1b[0m";
 // Other colors
 pub const GRAY: &str = "\x1b[90m";
 
-pub const HELP: &str = r"
-USAGE:
-    nrush [command] or [options] # Can't use both at the same time
-COMMANDS:
-    help        Prints help information
-    source      Prints source code location
-OPTIONS:
-    -u, --update            Update all packages
-    -i, --interactive       Interactive mode
-    -p, --path              Path to package.json
-    -s, --semver            Update up to the specified semver type (major, minor, patch)
-    --include               Include dev and/or peer dependencies, eg. --include dev,peer
-    --skip-ranges           Skip version ranges (e.g. ^, ~, >=, <=)
-";
+pub const EXIT_CODES: &str = r"EXIT CODES (--check):
+    0   Everything is up to date, or only has updates below --error-level
+    1   Something is outdated
+    2   A package couldn't be looked up, or the arguments or .nrushrc.json are invalid
+    3   The install run by --install failed, with or without --check";
 
 pub const ABOUT: &str = r"
 AUTHOR:
@@ -34,4 +25,23 @@ SOURCE:
 // Constants for dependency keys
 pub const DEPENDENCIES: &str = "dependencies";
 pub const DEV_DEPENDENCIES: &str = "devDependencies";
-pub const PEER_DEPENDENCIES: &str = "peerDependencies";
\ No newline at end of file
+pub const PEER_DEPENDENCIES: &str = "peerDependencies";
+
+// Registry request limits
+pub const DEFAULT_CONCURRENCY: usize = 16;
+pub const REQUEST_TIMEOUT_SECS: u64 = 30;
+pub const MAX_RETRIES: u32 = 3;
+pub const RETRY_BASE_DELAY_MS: u64 = 500;
+pub const MAX_RETRY_DELAY_MS: u64 = 30_000;
+
+// Packuments younger than this are used from the cache without asking the registry
+pub const DEFAULT_CACHE_TTL_MINUTES: u64 = 10;
+
+// Exit codes of --check
+pub const EXIT_UP_TO_DATE: i32 = 0;
+pub const EXIT_OUTDATED: i32 = 1;
+pub const EXIT_FETCH_ERROR: i32 = 2;
+// Same code clap uses for invalid arguments
+pub const EXIT_INVALID_INPUT: i32 = 2;
+// Kept apart from the --check codes so a failed install can't pass for an outdated project
+pub const EXIT_INSTALL_FAILED: i32 = 3;
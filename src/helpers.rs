@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use crossterm::event::{KeyCode, KeyEvent, read};
 
 pub fn prompt_confirm(message: &str, default: bool) -> bool {
@@ -12,4 +14,64 @@ pub fn prompt_confirm(message: &str, default: bool) -> bool {
 			}
 		}
 	}
-}
\ No newline at end of file
+}
+
+// Function to describe a duration the way people say it, eg. "3 hours"
+pub fn format_age(age: Duration) -> String {
+	let seconds = age.as_secs();
+	let (amount, unit) = match seconds {
+		0..=59 => (seconds, "second"),
+		60..=3599 => (seconds / 60, "minute"),
+		3600..=86399 => (seconds / 3600, "hour"),
+		_ => (seconds / 86400, "day"),
+	};
+	format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+// Function to parse an ISO 8601 UTC timestamp like the registry's "2024-05-01T12:30:00.000Z"
+// into seconds since the epoch
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+	let (date, time) = timestamp.trim().split_once('T')?;
+	let time = time.trim_end_matches('Z');
+	let time = time.split_once('.').map_or(time, |(time, _)| time);
+
+	let parse = |text: &str, separator| -> Option<[i64; 3]> {
+		let mut parts = text.splitn(3, separator).map(|part| part.parse::<i64>().ok());
+		Some([parts.next()??, parts.next()??, parts.next()??])
+	};
+	let [year, month, day] = parse(date, '-')?;
+	let [hour, minute, second] = parse(time, ':')?;
+
+	// Days since the epoch, using the days_from_civil algorithm with years starting in March
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146_097 + day_of_era - 719_468;
+
+	u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_registry_timestamps() {
+		let cases = [
+			("1970-01-01T00:00:00.000Z", Some(0)),
+			("1999-12-31T23:59:59Z", Some(946_684_799)),
+			("2000-03-01T00:00:00.000Z", Some(951_868_800)),
+			("2024-02-29T12:30:00.000Z", Some(1_709_209_800)),
+			("2024-05-01T12:30:00.123Z", Some(1_714_566_600)),
+			("2100-03-01T00:00:00Z", Some(4_107_542_400)),
+			("1969-12-31T23:59:59.000Z", None),
+			("2024-05-01", None),
+			("2024-05-01Tnoon", None),
+		];
+		for (timestamp, expected) in cases {
+			assert_eq!(parse_timestamp(timestamp), expected, "{}", timestamp);
+		}
+	}
+}
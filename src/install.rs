@@ -0,0 +1,125 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{self, Command};
+
+use serde_json::Value;
+
+use crate::arguments::Install;
+use crate::constants::{EXIT_INSTALL_FAILED, GRAY, RESET};
+use crate::helpers::prompt_confirm;
+use crate::manifest::MANIFEST_FILE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+    Bun,
+}
+
+// Lockfiles and the package manager that writes them, in the order they're looked for
+const LOCKFILES: &[(&str, PackageManager)] = &[
+    ("bun.lock", PackageManager::Bun),
+    ("bun.lockb", PackageManager::Bun),
+    ("pnpm-lock.yaml", PackageManager::Pnpm),
+    ("yarn.lock", PackageManager::Yarn),
+    ("package-lock.json", PackageManager::Npm),
+    ("npm-shrinkwrap.json", PackageManager::Npm),
+];
+
+impl PackageManager {
+    // Function to find the package manager of a workspace. The `packageManager` field of
+    // its package.json, eg. "pnpm@9.1.0", wins over the lockfile, and npm is used if
+    // there's neither
+    pub fn detect(root_dir: &Path) -> PackageManager {
+        let root_json = fs::read_to_string(root_dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .unwrap_or_default();
+        let declared = root_json["packageManager"]
+            .as_str()
+            .and_then(|field| field.split('@').next())
+            .and_then(PackageManager::from_name);
+
+        declared
+            .or_else(|| {
+                LOCKFILES
+                    .iter()
+                    .find(|(file, _)| root_dir.join(file).is_file())
+                    .map(|(_, manager)| *manager)
+            })
+            .unwrap_or(PackageManager::Npm)
+    }
+
+    fn from_name(name: &str) -> Option<PackageManager> {
+        match name.trim() {
+            "npm" => Some(PackageManager::Npm),
+            "yarn" => Some(PackageManager::Yarn),
+            "pnpm" => Some(PackageManager::Pnpm),
+            "bun" => Some(PackageManager::Bun),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Bun => "bun",
+        }
+    }
+}
+
+// Function to run the package manager's install in the workspace root after package.json
+// was written, if --install asks for it. Its output is streamed as it comes (to stderr with --format json
+// so the report stays parseable), and a failed install exits nrush with EXIT_INSTALL_FAILED
+pub fn install_after_update(
+    install: Option<Install>,
+    root_dir: &Path,
+    can_prompt: bool,
+    json: bool,
+) {
+    let manager = PackageManager::detect(root_dir);
+    match install {
+        None | Some(Install::Never) => return,
+        Some(Install::Prompt) if !can_prompt => return,
+        Some(Install::Prompt) => {
+            let message = format!("\nDo you want to run {} install? (y/N)", manager.name());
+            if !prompt_confirm(&message, false) {
+                return;
+            }
+        }
+        Some(Install::Always) => (),
+    }
+
+    if !json {
+        println!("{}Running {} install...{}", GRAY, manager.name(), RESET);
+    }
+
+    // npm, yarn and pnpm are .cmd scripts on Windows, which only cmd can start
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", manager.name()]);
+        command
+    } else {
+        Command::new(manager.name())
+    };
+    command.arg("install").current_dir(root_dir);
+    if json {
+        command.stdout(io::stderr());
+    }
+
+    match command.status() {
+        Ok(status) if status.success() => (),
+        Ok(status) => {
+            eprintln!("{} install failed ({})", manager.name(), status);
+            process::exit(EXIT_INSTALL_FAILED);
+        }
+        Err(e) => {
+            eprintln!("Unable to run {} install: {}", manager.name(), e);
+            process::exit(EXIT_INSTALL_FAILED);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use semver::Version;
+use serde_json::Value;
+
+use crate::workspace::{split_key, strip_comment, unquote};
+
+// Lockfiles nrush can read, in the order they're looked for
+const LOCKFILES: &[&str] = &[
+    "bun.lock",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "package-lock.json",
+];
+
+// The versions a lockfile says are installed
+pub struct Lockfile {
+    pub file: &'static str,
+    // Versions by the directory of the package.json that depends on them, relative to the
+    // root ("" for the root itself), and the package name
+    importers: HashMap<(String, String), String>,
+    // Whether the root's packages are shared with workspace members, like npm and bun hoist them
+    hoisted: bool,
+    // Versions by yarn descriptor, eg. "react@npm:^18.2.0"
+    descriptors: HashMap<String, String>,
+}
+
+impl Lockfile {
+    // Function to read the first lockfile found in the workspace root. Lockfiles that
+    // can't be read or parsed are treated as missing, nrush only uses them for display
+    pub fn load(root_dir: &Path) -> Option<Lockfile> {
+        let (file, content) = LOCKFILES
+            .iter()
+            .find_map(|file| Some((*file, fs::read_to_string(root_dir.join(file)).ok()?)))?;
+        let mut lockfile = Lockfile {
+            file,
+            importers: HashMap::new(),
+            hoisted: false,
+            descriptors: HashMap::new(),
+        };
+
+        match file {
+            "bun.lock" => lockfile.read_bun(&content)?,
+            "pnpm-lock.yaml" => lockfile.read_pnpm(&content),
+            "yarn.lock" => lockfile.read_yarn(&content),
+            _ => lockfile.read_npm(&content)?,
+        }
+        Some(lockfile)
+    }
+
+    // Function to get the installed version of a dependency declared as `spec` by the
+    // package.json in the importer directory
+    pub fn installed(&self, importer: &str, package: &str, spec: &str) -> Option<&str> {
+        let lookup = |importer: &str| {
+            self.importers
+                .get(&(importer.to_string(), package.to_string()))
+        };
+        lookup(importer)
+            .or_else(|| lookup("").filter(|_| self.hoisted))
+            .or_else(|| self.descriptors.get(&format!("{}@{}", package, spec)))
+            .or_else(|| self.descriptors.get(&format!("{}@npm:{}", package, spec)))
+            .map(|version| version.as_str())
+    }
+
+    fn insert(&mut self, importer: &str, package: &str, version: &str) {
+        if Version::parse(version).is_ok() {
+            self.importers.insert(
+                (importer.to_string(), package.to_string()),
+                version.to_string(),
+            );
+        }
+    }
+
+    // package-lock.json v2 and v3 list every installed package by its path,
+    // eg. "node_modules/react" or "packages/ui/node_modules/react"
+    fn read_npm(&mut self, content: &str) -> Option<()> {
+        let json = serde_json::from_str::<Value>(content).ok()?;
+        self.hoisted = true;
+        for (path, package) in json["packages"].as_object()? {
+            let Some((importer, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            let importer = importer.trim_end_matches('/');
+            if importer.contains("node_modules") {
+                continue; // A dependency of a dependency
+            }
+            if let Some(version) = package["version"].as_str() {
+                self.insert(importer, name, version);
+            }
+        }
+        Some(())
+    }
+
+    // bun.lock is JSON with trailing commas. Packages are keyed by name, or by the
+    // workspace name and package name when a member has its own copy
+    fn read_bun(&mut self, content: &str) -> Option<()> {
+        let json = serde_json::from_str::<Value>(&strip_trailing_commas(content)).ok()?;
+        self.hoisted = true;
+        let workspaces = json["workspaces"]
+            .as_object()
+            .map(|workspaces| {
+                workspaces
+                    .iter()
+                    .filter_map(|(path, workspace)| Some((workspace["name"].as_str()?, path)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        for (key, package) in json["packages"].as_object()? {
+            // The first item is "name@version"
+            let Some((_, version)) = package[0].as_str().and_then(|id| id.rsplit_once('@')) else {
+                continue;
+            };
+            let member = workspaces.iter().find_map(|(name, path)| {
+                Some((path.as_str(), key.strip_prefix(name)?.strip_prefix('/')?))
+            });
+            let (importer, name) = member.unwrap_or(("", key));
+            self.insert(importer, name, version);
+        }
+        Some(())
+    }
+
+    // yarn.lock lists the descriptors each version was resolved for. Classic writes
+    // `"react@^18.2.0", react@^18.3.0:` and `version "18.3.1"`, berry writes
+    // `"react@npm:^18.2.0, react@npm:^18.3.0":` and `version: 18.3.1`
+    fn read_yarn(&mut self, content: &str) {
+        let mut descriptors: Vec<String> = vec![];
+        for line in content.lines() {
+            let text = strip_comment(line);
+            if text.is_empty() {
+                continue;
+            }
+
+            if !text.starts_with([' ', '\t']) {
+                descriptors = text
+                    .trim_end_matches(':')
+                    .replace('"', "")
+                    .split(',')
+                    .map(|descriptor| descriptor.trim().to_string())
+                    .collect();
+                continue;
+            }
+
+            let text = text.trim_start();
+            let version = text
+                .strip_prefix("version:")
+                .or_else(|| text.strip_prefix("version "))
+                .map(unquote);
+            if let Some(version) = version {
+                for descriptor in descriptors.drain(..) {
+                    self.descriptors.insert(descriptor, version.clone());
+                }
+            }
+        }
+    }
+
+    // pnpm-lock.yaml lists what each importer resolved under `importers`, or at the top
+    // level for lockfiles of a single project. From v6 on each dependency has `specifier`
+    // and `version` keys, before that the version is the value itself
+    fn read_pnpm(&mut self, content: &str) {
+        const SECTIONS: &[&str] = &["dependencies", "devDependencies", "optionalDependencies"];
+        let mut path: Vec<(usize, String)> = vec![];
+
+        for line in content.lines() {
+            let text = strip_comment(line);
+            let trimmed = text.trim_start();
+            let indent = text.len() - trimmed.len();
+            let Some((key, value)) = split_key(trimmed) else {
+                continue;
+            };
+            let value = unquote(&trimmed[value]);
+
+            path.retain(|(parent_indent, _)| *parent_indent < indent);
+            let keys = path
+                .iter()
+                .map(|(_, key)| key.as_str())
+                .chain([key.as_str()])
+                .collect::<Vec<_>>();
+            if value.is_empty() {
+                path.push((indent, key.clone()));
+                continue;
+            }
+
+            let (importer, section, package) = match keys.as_slice() {
+                ["importers", importer, section, package, "version"]
+                | ["importers", importer, section, package] => (*importer, *section, *package),
+                [section, package, "version"] | [section, package] => ("", *section, *package),
+                _ => continue,
+            };
+            if SECTIONS.contains(&section) {
+                let importer = if importer == "." { "" } else { importer };
+                // Peer dependencies are appended in parentheses, eg. 18.3.1(react@18.3.1)
+                let version = value.split('(').next().unwrap_or_default();
+                self.insert(importer, package, version);
+            }
+        }
+    }
+}
+
+// Function to drop the commas before a closing bracket or brace, outside of strings
+fn strip_trailing_commas(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut pending_comma = None;
+
+    for c in content.chars() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match c {
+            ',' => {
+                if let Some(whitespace) = pending_comma.replace(String::new()) {
+                    stripped.push(',');
+                    stripped.push_str(&whitespace);
+                }
+            }
+            c if c.is_whitespace() && pending_comma.is_some() => {
+                pending_comma.as_mut().unwrap().push(c);
+            }
+            _ => {
+                if let Some(whitespace) = pending_comma.take() {
+                    if c != '}' && c != ']' {
+                        stripped.push(',');
+                    }
+                    stripped.push_str(&whitespace);
+                }
+                if c == '"' {
+                    in_string = true;
+                }
+                stripped.push(c);
+            }
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Function to load a lockfile from a fresh directory under the system temp dir
+    fn load(file: &str, content: &str) -> Lockfile {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "nrush-lockfile-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), content).unwrap();
+        let lockfile = Lockfile::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        lockfile.unwrap_or_else(|| panic!("{} should parse", file))
+    }
+
+    #[test]
+    fn reads_package_lock() {
+        let lockfile = load(
+            "package-lock.json",
+            r#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": { "name": "app" },
+    "node_modules/react": { "version": "18.3.1" },
+    "node_modules/@scope/ui": { "version": "1.2.0" },
+    "node_modules/old/node_modules/react": { "version": "16.14.0" },
+    "packages/ui": { "name": "ui" },
+    "packages/ui/node_modules/react": { "version": "17.0.2" }
+  }
+}"#,
+        );
+        assert_eq!(lockfile.installed("", "react", "^18.2.0"), Some("18.3.1"));
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.0.0"), Some("1.2.0"));
+        assert_eq!(
+            lockfile.installed("packages/ui", "react", "^17.0.0"),
+            Some("17.0.2")
+        );
+        // Members without their own copy use the hoisted one
+        assert_eq!(
+            lockfile.installed("packages/web", "react", "^18.0.0"),
+            Some("18.3.1")
+        );
+        assert_eq!(lockfile.installed("", "old", "^1.0.0"), None);
+    }
+
+    #[test]
+    fn reads_classic_yarn_lock() {
+        let lockfile = load(
+            "yarn.lock",
+            "# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.\r\n# yarn lockfile v1\r\n\r\n\r\n\"@scope/ui@^1.0.0\", \"@scope/ui@^1.1.0\":\r\n  version \"1.2.0\"\r\n  resolved \"https://registry.yarnpkg.com/@scope/ui/-/ui-1.2.0.tgz\"\r\n\r\nreact@^18.2.0:\r\n  version \"18.3.1\"\r\n",
+        );
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.0.0"), Some("1.2.0"));
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.1.0"), Some("1.2.0"));
+        assert_eq!(
+            lockfile.installed("packages/ui", "react", "^18.2.0"),
+            Some("18.3.1")
+        );
+        assert_eq!(lockfile.installed("", "react", "^18.3.0"), None);
+    }
+
+    #[test]
+    fn reads_berry_yarn_lock() {
+        let lockfile = load(
+            "yarn.lock",
+            r#"# This file is generated by running "yarn install" inside your project.
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"@scope/ui@npm:^1.0.0":
+  version: 1.2.0
+  resolution: "@scope/ui@npm:1.2.0"
+
+"react@npm:^18.2.0, react@npm:^18.3.0":
+  version: 18.3.1
+  resolution: "react@npm:18.3.1"
+"#,
+        );
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.0.0"), Some("1.2.0"));
+        assert_eq!(lockfile.installed("", "react", "^18.2.0"), Some("18.3.1"));
+        assert_eq!(lockfile.installed("", "react", "^18.3.0"), Some("18.3.1"));
+        assert_eq!(lockfile.installed("", "react", "^17.0.0"), None);
+    }
+
+    #[test]
+    fn reads_pnpm_v6_lock() {
+        let lockfile = load(
+            "pnpm-lock.yaml",
+            "lockfileVersion: '6.0'
+
+importers:
+
+  .:
+    dependencies:
+      react:
+        specifier: ^18.2.0
+        version: 18.3.1
+
+  packages/ui:
+    devDependencies:
+      react-dom:
+        specifier: ^18.2.0
+        version: 18.3.1(react@18.3.1)
+
+packages:
+
+  /react@18.3.1:
+    resolution: {integrity: sha512-x}
+    dev: false
+",
+        );
+        assert_eq!(lockfile.installed("", "react", "^18.2.0"), Some("18.3.1"));
+        assert_eq!(
+            lockfile.installed("packages/ui", "react-dom", "^18.2.0"),
+            Some("18.3.1")
+        );
+        // pnpm doesn't hoist the root's dependencies into members
+        assert_eq!(lockfile.installed("packages/ui", "react", "^18.2.0"), None);
+    }
+
+    #[test]
+    fn reads_pnpm_v9_lock() {
+        let lockfile = load(
+            "pnpm-lock.yaml",
+            "lockfileVersion: '9.0'\r\n\r\nsettings:\r\n  autoInstallPeers: true\r\n\r\nimporters:\r\n\r\n  .:\r\n    dependencies:\r\n      '@scope/ui':\r\n        specifier: ^1.0.0\r\n        version: 1.2.0\r\n\r\n  packages/ui:\r\n    dependencies:\r\n      react:\r\n        specifier: ^17.0.0\r\n        version: 17.0.2 # pinned\r\n\r\npackages:\r\n\r\n  react@17.0.2:\r\n    resolution: {integrity: sha512-x}\r\n\r\nsnapshots:\r\n\r\n  react@17.0.2:\r\n    dependencies:\r\n      loose-envify: 1.4.0\r\n",
+        );
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.0.0"), Some("1.2.0"));
+        assert_eq!(
+            lockfile.installed("packages/ui", "react", "^17.0.0"),
+            Some("17.0.2")
+        );
+        assert_eq!(lockfile.installed("", "loose-envify", "^1.0.0"), None);
+    }
+
+    #[test]
+    fn reads_pnpm_v5_lock_of_a_single_project() {
+        let lockfile = load(
+            "pnpm-lock.yaml",
+            "lockfileVersion: 5.4
+
+specifiers:
+  react: ^18.2.0
+
+dependencies:
+  react: 18.3.1
+",
+        );
+        assert_eq!(lockfile.installed("", "react", "^18.2.0"), Some("18.3.1"));
+    }
+
+    #[test]
+    fn reads_bun_lock() {
+        let lockfile = load(
+            "bun.lock",
+            r#"{
+  "lockfileVersion": 1,
+  "workspaces": {
+    "": {
+      "name": "app",
+      "dependencies": {
+        "react": "^18.2.0",
+      },
+    },
+    "packages/ui": {
+      "name": "ui",
+      "dependencies": {
+        "react": "^17.0.0",
+      },
+    },
+  },
+  "packages": {
+    "react": ["react@18.3.1", "", { "dependencies": { "loose-envify": "^1.1.0" } }, "sha512-a,]"],
+    "ui/react": ["react@17.0.2", "", {}, "sha512-b,}"],
+    "@scope/ui": ["@scope/ui@1.2.0", "", {}, "sha512-c\",]"],
+  },
+}
+"#,
+        );
+        assert_eq!(lockfile.installed("", "react", "^18.2.0"), Some("18.3.1"));
+        assert_eq!(lockfile.installed("", "@scope/ui", "^1.0.0"), Some("1.2.0"));
+        assert_eq!(
+            lockfile.installed("packages/ui", "react", "^17.0.0"),
+            Some("17.0.2")
+        );
+        assert_eq!(
+            lockfile.installed("packages/web", "react", "^18.0.0"),
+            Some("18.3.1")
+        );
+    }
+
+    #[test]
+    fn strips_trailing_commas_outside_strings() {
+        assert_eq!(
+            strip_trailing_commas(r#"{"a": "x,}", "b": [1, 2, ], "c": "q\",]",}"#),
+            r#"{"a": "x,}", "b": [1, 2 ], "c": "q\",]"}"#
+        );
+        assert_eq!(
+            strip_trailing_commas("{\r\n\t\"a\": [\r\n\t\t1,\r\n\t],\r\n}\r\n"),
+            "{\r\n\t\"a\": [\r\n\t\t1\r\n\t]\r\n}\r\n"
+        );
+        assert_eq!(strip_trailing_commas("[1, 2]"), "[1, 2]");
+    }
+}
@@ -1,216 +1,615 @@
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
 use std::path::PathBuf;
-use std::time::Instant;
-use std::{fs, io};
+use std::time::{Duration, Instant};
+use std::{io, process};
 
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::MultiSelect;
-use semver::Version;
-use serde_json::Value;
+use indicatif::ProgressDrawTarget;
 use tokio::main;
 
 use arguments::Include;
-use constants::{ABOUT, GRAY, HELP};
-
-use crate::arguments::{Arguments, Command};
-use crate::constants::{DEPENDENCIES, DEV_DEPENDENCIES, PEER_DEPENDENCIES, RESET};
-use crate::helpers::prompt_confirm;
-use crate::packages::{get_current_package_version, package_type, set_new_package_version};
+use constants::{ABOUT, GRAY};
+
+use crate::arguments::{Arguments, CacheCommand, Command, Format};
+use crate::cache::Cache;
+use crate::catalog::{catalog_reference, load_catalogs};
+use crate::client::{FetchError, RegistryClient};
+use crate::config::Config;
+use crate::constants::{EXIT_INVALID_INPUT, MAJOR, MINOR, RESET};
+use crate::helpers::{format_age, prompt_confirm};
+use crate::install::install_after_update;
+use crate::lockfile::Lockfile;
+use crate::manifest::{manifest_dir, resolve_manifest_path, write_changed, Manifest};
+use crate::npmrc::Npmrc;
+use crate::packages::{
+    apply_update, bump_kind, floor_version, get_current_package_version, is_update,
+    registry_dependencies,
+};
 use crate::progress::create_progress_bar;
+use crate::report::{check_exit_code, DependencyResult, DependencyType, Report};
+use crate::target::select_version;
+use crate::workspace::{
+    align_ranges, discover_members, is_alignable, relative_path, workspace_root,
+};
 
 mod arguments;
+mod cache;
+mod catalog;
+mod client;
+mod config;
 mod constants;
 mod helpers;
+mod install;
+mod lockfile;
+mod manifest;
+mod npmrc;
 mod packages;
 mod progress;
+mod range;
+mod report;
+mod specifier;
+mod target;
+mod workspace;
 
 #[main]
 async fn main() {
-    let path = PathBuf::from("package.json");
     let args = Arguments::new();
     let current_time = Instant::now(); // Used if -u or --update is passed
 
-    if !args.command.is_none() {
-        match args.command.unwrap() {
-            Command::Help => {
-                println!("{}", HELP);
-            }
-            Command::About => {
-                println!("{}", ABOUT);
+    match &args.command {
+        Some(Command::About) => {
+            println!("{}", ABOUT);
+            return;
+        }
+        Some(Command::Cache {
+            command: CacheCommand::Clean,
+        }) => {
+            match cache::clean() {
+                Ok(dir) => println!("Cleaned {}", dir.display()),
+                Err(e) => {
+                    eprintln!("Unable to clean the cache: {}", e);
+                    process::exit(1);
+                }
             }
+            return;
         }
-        return;
+        _ => (),
     }
 
-    let file_content = match fs::read_to_string(&path) {
-        Ok(content) => content,
-        Err(_) => {
+    // JSON output is for other programs, so it never clears the screen or prompts.
+    // Neither does --check, `nrush outdated`, or anything that doesn't run in a terminal
+    let json = args.format == Format::Json;
+    let list_only = args.check || args.list_only();
+    let can_prompt = !json && !list_only && io::stdin().is_terminal();
+    let interactive = args.interactive && can_prompt;
+
+    let path = match resolve_manifest_path(&args.path) {
+        Some(path) => path,
+        None if !can_prompt => {
+            eprintln!("No package.json found in the current path.");
+            process::exit(1);
+        }
+        None => {
             println!("No package.json found in the current path. Please specify the path to package.json:");
             let mut user_input = String::new();
             io::stdin()
                 .read_line(&mut user_input)
                 .expect("Failed to read user input");
-            user_input.trim().to_string()
+            resolve_manifest_path(&PathBuf::from(user_input.trim())).unwrap_or_else(|| {
+                eprintln!("No package.json found at the specified path.");
+                process::exit(1);
+            })
         }
     };
 
-    let mut json_data: Value = serde_json::from_str(&file_content).expect("Unable to parse JSON");
-
-    let package_names: Vec<String> = match json_data["dependencies"].as_object() {
-        Some(obj) => obj.keys().map(|x| x.to_string()).collect(),
-        None => {
-            Vec::new() // Return empty vector if no dependencies are found
+    let root_dir = manifest_dir(&path);
+    let mut manifests = vec![Manifest::load(&path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })];
+    if args.workspaces {
+        for member in discover_members(&root_dir, &manifests[0].json) {
+            match Manifest::load(&member) {
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => eprintln!("{}", e),
+            }
         }
-    };
-
-    let mut dev_package_names: Vec<String> = Vec::new();
-    let mut peer_package_names: Vec<String> = Vec::new();
+        for manifest in &mut manifests {
+            let relative = relative_path(&root_dir, &manifest_dir(&manifest.path));
+            let relative = if relative.is_empty() { "." } else { &relative };
+            manifest.label = Some(manifest.name_or(relative));
+        }
+    }
+    let workspace_count = manifests.len();
+    // Catalogs and the lockfile sit at the root of the workspace, which can be above the project
+    let workspace_root = workspace_root(&root_dir);
+    let catalogs = load_catalogs(&workspace_root, &mut manifests);
 
-    let mut fetch_version_tasks = package_names
+    // Members depending on each other by version are resolved locally, not from the registry
+    let member_names = manifests[1..]
         .iter()
-        .cloned()
-        .map(|package| packages::fetch_package_version(package))
-        .collect::<Vec<_>>();
-
+        .filter_map(|manifest| manifest.json["name"].as_str())
+        .collect::<HashSet<_>>();
+
+    let lockfile = Lockfile::load(&workspace_root);
+
+    let npmrc = Npmrc::load(&root_dir, args.registry.as_deref());
+    let config = Config::load(&root_dir).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(EXIT_INVALID_INPUT);
+    });
+    // Offline mode has nothing to go on without the cache, so it ignores --no-cache
+    let cache = (!args.no_cache || args.offline)
+        .then(|| Cache::new(Duration::from_secs(args.cache_ttl.saturating_mul(60))));
+    let client = RegistryClient::new(npmrc, args.concurrency, cache, args.offline);
+    let cooldown = args
+        .cooldown
+        .or(config.cooldown)
+        .filter(|days| *days > 0)
+        .map(|days| Duration::from_secs(days * 86_400));
+    // Publish times are only in the full document
+    let full_metadata = args.target.needs_time() || cooldown.is_some();
+
+    let mut dependency_types = vec![DependencyType::Prod];
     if let Some(includes) = &args.include {
         if includes.contains(&Include::Dev) {
-            dev_package_names = match json_data[DEV_DEPENDENCIES].as_object() {
-                Some(obj) => obj.keys().map(|x| x.to_string()).collect(),
-                None => {
-                    Vec::new() // Return empty vector if no dev dependencies are found
-                }
-            };
+            dependency_types.push(DependencyType::Dev);
+        }
+        if includes.contains(&Include::Peer) {
+            dependency_types.push(DependencyType::Peer);
+        }
+    }
 
-            fetch_version_tasks.append(
-                &mut dev_package_names
-                    .iter()
-                    .cloned() // Clone each package name
-                    .map(|package| packages::fetch_package_version(package))
-                    .collect::<Vec<_>>(),
-            );
+    // Every dependency section of every manifest, and every catalog
+    let mut sections = vec![];
+    for (index, manifest) in manifests.iter().enumerate() {
+        for dependency_type in &dependency_types {
+            let section = vec![dependency_type.key().to_string()];
+            sections.push((index, *dependency_type, section, manifest.label.clone()));
         }
+    }
+    for catalog in &catalogs {
+        let section = catalog.section.clone();
+        let label = Some(catalog.label());
+        sections.push((catalog.manifest, DependencyType::Catalog, section, label));
+    }
 
-        if includes.contains(&Include::Peer) {
-            peer_package_names = match json_data[PEER_DEPENDENCIES].as_object() {
-                Some(obj) => obj.keys().map(|x| x.to_string()).collect(),
-                None => {
-                    Vec::new() // Return empty vector if no peer dependencies are found
-                }
-            };
+    // The dependencies to check, and the registry names to look up, once each
+    let mut skipped = vec![];
+    let mut dependencies = vec![];
+    let mut names = vec![];
+    for (index, dependency_type, section, label) in sections {
+        let (key, parent) = section.split_last().expect("Sections aren't empty");
+        let parent = parent
+            .iter()
+            .fold(&manifests[index].json, |value, key| &value[key.as_str()]);
+        let declared = &parent[key.as_str()];
+        let manifest_dir = manifest_dir(&manifests[index].path);
+        let importer = relative_path(
+            &workspace_root,
+            &manifest_dir.canonicalize().unwrap_or(manifest_dir),
+        );
+        let new_result = |package: &str| {
+            let current = get_current_package_version(package, declared);
+            let mut result = DependencyResult::new(package, dependency_type, &current);
+            if dependency_type != DependencyType::Catalog {
+                result.installed = lockfile
+                    .as_ref()
+                    .and_then(|lockfile| lockfile.installed(&importer, package, &current))
+                    .map(|version| version.to_string());
+            }
+            result.workspace = label.clone();
+            result.manifest = index;
+            result.section = section.clone();
+            result
+        };
 
-            fetch_version_tasks.append(
-                &mut peer_package_names
-                    .iter()
-                    .cloned() // Clone each package name
-                    .map(|package| packages::fetch_package_version(package))
-                    .collect::<Vec<_>>(),
-            );
+        let mut skipped_here = vec![];
+        for (package, name) in registry_dependencies(parent, key, &mut skipped_here) {
+            if member_names.contains(name.as_str()) {
+                skipped_here.push((package, "workspace package"));
+                continue;
+            }
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
+            dependencies.push((new_result(&package), name));
+        }
+        for (package, mut reason) in skipped_here {
+            // catalog: specifiers are checked through the catalog they refer to
+            if let Some(name) = catalog_reference(declared[&package].as_str().unwrap_or("")) {
+                let found = catalogs.iter().any(|catalog| {
+                    catalog.name == name
+                        && catalog
+                            .entries(&manifests)
+                            .is_some_and(|entries| entries.contains_key(&package))
+                });
+                if found {
+                    continue;
+                }
+                reason = "catalog entry not found";
+            }
+            let mut result = new_result(&package);
+            result.skipped = Some(reason);
+            skipped.push(result);
         }
     }
     let time_elapsed = Instant::now();
 
-    let progress_bar = create_progress_bar(
-        fetch_version_tasks.len() as u64,
-        "Fetching package versions...",
-    );
-
-    let fetch_version_results =
-        futures::future::join_all(fetch_version_tasks.into_iter().map(|task| {
-            let results = progress_bar.clone();
-            async move {
-                let result = task.await;
-                results.inc(1);
-                result
-            }
-        }))
-        .await;
+    let progress_bar = create_progress_bar(names.len() as u64, "Fetching package versions...");
+    if json {
+        progress_bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    let fetch_version_results = futures::future::join_all(names.iter().map(|name| {
+        let results = progress_bar.clone();
+        let task = packages::fetch_packument(name.clone(), full_metadata, &client);
+        async move {
+            let result = task.await;
+            results.inc(1);
+            result
+        }
+    }))
+    .await
+    .into_iter()
+    .collect::<HashMap<_, _>>();
     progress_bar.finish_and_clear();
 
-    fn get_include_message(include: Option<&Vec<Include>>) -> &str {
-        match include {
-            Some(v) if v.contains(&Include::Dev) && v.contains(&Include::Peer) => {
-                " including dev and peer dependencies"
+    if !json {
+        fn get_include_message(include: Option<&Vec<Include>>) -> &str {
+            match include {
+                Some(v) if v.contains(&Include::Dev) && v.contains(&Include::Peer) => {
+                    " including dev and peer dependencies"
+                }
+                Some(v) if v.contains(&Include::Dev) => " including dev dependencies",
+                Some(v) if v.contains(&Include::Peer) => " including peer dependencies",
+                _ => "",
+            }
+        }
+
+        if io::stdout().is_terminal() {
+            print!("\x1B[2J\x1B[1;1H");
+        }
+        let include_message = get_include_message(args.include.as_ref());
+        let workspace_message = if args.workspaces {
+            format!(" across {} workspaces", workspace_count)
+        } else {
+            String::new()
+        };
+
+        println!(
+            "{}Checked {} packages{} in {}ms{} (target: {}).{}",
+            GRAY,
+            fetch_version_results.len(),
+            workspace_message,
+            time_elapsed.elapsed().as_millis(),
+            include_message,
+            args.target.name(),
+            RESET,
+        );
+
+        for result in &skipped {
+            if let Some(reason) = result.skipped {
+                println!(
+                    "{}Skipped {}{} ({}).{}",
+                    GRAY,
+                    result.name,
+                    in_workspace(result),
+                    reason,
+                    RESET
+                );
+            }
+        }
+
+        if args.installed {
+            match &lockfile {
+                Some(lockfile) => println!(
+                    "{}Comparing against the versions installed according to {}.{}",
+                    GRAY, lockfile.file, RESET
+                ),
+                None => println!(
+                    "{}No lockfile found, comparing against the declared ranges.{}",
+                    GRAY, RESET
+                ),
+            }
+        }
+
+        if args.offline {
+            let oldest = fetch_version_results
+                .values()
+                .filter_map(|result| result.as_ref().ok()?.cache_age)
+                .max();
+            if let Some(oldest) = oldest {
+                println!(
+                    "{}Offline: using cached registry data up to {} old.{}",
+                    GRAY,
+                    format_age(oldest),
+                    RESET
+                );
+            }
+        }
+
+        for (name, result) in &fetch_version_results {
+            match result {
+                Err(FetchError::NotCached) => {
+                    println!("{}No cached data for {}.{}", GRAY, name, RESET);
+                }
+                Err(e) => println!("Error fetching package version for {}: {}", name, e),
+                Ok(_) => (),
             }
-            Some(v) if v.contains(&Include::Dev) => " including dev dependencies",
-            Some(v) if v.contains(&Include::Peer) => " including peer dependencies",
-            _ => "",
         }
     }
 
-    print!("\x1B[2J\x1B[1;1H");
-    let include_message = get_include_message(args.include.as_ref());
-
-    println!(
-        "{}Checked {} packages in {}ms{}.{}",
-        GRAY,
-        fetch_version_results.len(),
-        time_elapsed.elapsed().as_millis(),
-        include_message,
-        RESET,
-    );
-
-    let mut to_update = vec![];
-    for result in fetch_version_results {
-        match result {
-            Ok((package, version)) => {
-                let is_dev = dev_package_names.contains(&package);
-                let is_peer = peer_package_names.contains(&package);
-                let current_version = get_current_package_version(
-                    &package,
-                    &json_data,
-                    &dev_package_names,
-                    &peer_package_names,
+    let mut results = vec![];
+    for (mut dependency, name) in dependencies {
+        let current_version = dependency.current.clone();
+        let package = dependency.name.clone();
+        match &fetch_version_results[&name] {
+            Ok(packument) => {
+                if let Some(current) = floor_version(&current_version) {
+                    dependency.deprecated = packument
+                        .deprecation(&current.to_string())
+                        .map(|message| message.to_string());
+                }
+                let tag = config.tag_for(&package, args.tag.as_deref());
+                let selection = select_version(
+                    packument,
+                    &current_version,
+                    args.target,
+                    tag,
+                    args.pre,
+                    cooldown,
                 );
-                let semver_current_version =
-                    Version::parse(&packages::normalize_version(&current_version));
-                let semver_latest_version = Version::parse(&packages::normalize_version(&version));
-                if let (Ok(curr_ver), Ok(latest_ver)) =
-                    (semver_current_version, semver_latest_version)
-                {
-                    if latest_ver > curr_ver {
-                        to_update.push((package.clone(), version, is_dev, is_peer));
+                if let Some(version) = selection.held_back {
+                    dependency.held_back_age = packument.age(&version);
+                    dependency.held_back = Some(version);
+                }
+                if let Some(version) = selection.version {
+                    // With --installed, the jump is measured from what's installed, and
+                    // versions that are already installed aren't offered
+                    let base = dependency
+                        .installed
+                        .clone()
+                        .filter(|_| args.installed)
+                        .unwrap_or(current_version.clone());
+                    if is_update(
+                        &current_version,
+                        &version,
+                        args.update_any,
+                        args.range_policy,
+                    ) && bump_kind(&base, &version).is_some()
+                    {
+                        dependency.bump = bump_kind(&base, &version);
                     }
-                } else if current_version == "*" && args.update_any {
-                    to_update.push((package.clone(), version, is_dev, is_peer));
+                    dependency.target = Some(version);
                 }
             }
-            Err(e) => {
-                println!("Error fetching package version: {}", e);
+            Err(e) => dependency.error = Some(e.to_string()),
+        }
+        results.push(dependency);
+    }
+
+    let mismatched = if args.consistent {
+        align_ranges(&mut results, args.range_policy)
+    } else {
+        vec![]
+    };
+
+    let exit_code = check_exit_code(&results, args.error_level);
+
+    let install = || install_after_update(args.install, &workspace_root, can_prompt, json);
+
+    if json {
+        if args.update && !list_only {
+            for result in results.iter_mut().filter(|result| result.is_outdated()) {
+                apply_update(
+                    result,
+                    args.range_policy,
+                    &mut manifests[result.manifest].json,
+                );
             }
+            write_changed(&manifests).expect("Unable to write file");
+        }
+
+        results.append(&mut skipped);
+        let report = Report {
+            manifest: path.display().to_string(),
+            target: args.target.name(),
+            dependencies: &results,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Unable to serialize the report")
+        );
+        if results.iter().any(|result| result.updated) {
+            install();
+        }
+        if args.check {
+            process::exit(exit_code);
+        }
+        return;
+    }
+
+    for result in results.iter().filter(|result| !result.is_outdated()) {
+        if let Some(version) = &result.held_back {
+            println!(
+                "{}Held back {} {}{}{} by the cooldown.{}",
+                GRAY,
+                result.name,
+                version,
+                in_workspace(result),
+                result
+                    .held_back_age
+                    .map(|age| format!(" ({} old)", format_age(age)))
+                    .unwrap_or_default(),
+                RESET
+            );
+        }
+        if let Some(message) = &result.deprecated {
+            println!(
+                "{}{} {}{} is deprecated: {}{}",
+                MAJOR,
+                result.name,
+                result.current,
+                in_workspace(result),
+                message,
+                RESET
+            );
         }
     }
 
+    for mismatch in &mismatched {
+        let group = results
+            .iter()
+            .filter(|result| is_alignable(result) && result.name == mismatch.name)
+            .collect::<Vec<_>>();
+        let declarations = group
+            .iter()
+            .map(|result| format!("{}{}", result.current, in_workspace(result)))
+            .collect::<Vec<_>>();
+        if mismatch.aligned {
+            println!(
+                "{}{} is declared as {}, aligning to {}.{}",
+                MINOR,
+                mismatch.name,
+                declarations.join(", "),
+                group[0].aligned.as_deref().unwrap_or_default(),
+                RESET
+            );
+        } else {
+            println!(
+                "{}{} is declared as {}, not aligning past the {} target.{}",
+                GRAY,
+                mismatch.name,
+                declarations.join(", "),
+                args.target.name(),
+                RESET
+            );
+        }
+    }
+
+    let mut to_update = results
+        .iter_mut()
+        .filter(|result| result.is_outdated())
+        .collect::<Vec<_>>();
+
     if to_update.is_empty() {
-        println!("Everything is up to date!");
+        // Packages that couldn't be looked up might be outdated, so don't claim otherwise
+        let unchecked = fetch_version_results
+            .values()
+            .filter(|result| result.is_err())
+            .count();
+        if unchecked == 0 {
+            println!("Everything is up to date!");
+        } else {
+            println!(
+                "{} package{} couldn't be checked{}.",
+                unchecked,
+                if unchecked == 1 { "" } else { "s" },
+                if args.offline {
+                    ", run without --offline to fetch them"
+                } else {
+                    ""
+                }
+            );
+        }
+        if args.check {
+            process::exit(exit_code);
+        }
         return;
     }
 
-    let generate_items =
-        |to_update: &Vec<(String, String, bool, bool)>, json_data: &Value| -> Vec<String> {
-            let mut items = vec![];
-            for &(ref package, ref version, ref is_dev, ref is_peer) in to_update {
-                let current_version = get_current_package_version(
-                    &package,
-                    json_data,
-                    &dev_package_names,
-                    &peer_package_names,
-                );
-                let type_str = package_type(is_dev, is_peer);
-                items.push(format!(
-                    "{}: {} -> {} ({})",
-                    package,
-                    current_version,
-                    packages::colorize_version(&current_version, &version),
-                    type_str
-                ));
-            }
-            items
-        };
+    let generate_items = |to_update: &[&mut DependencyResult]| -> Vec<String> {
+        let mut items = vec![];
+        for result in to_update {
+            let held_back = result
+                .held_back
+                .as_ref()
+                .map(|version| match result.held_back_age {
+                    Some(age) => format!(
+                        " {}[{} held back, {} old]{}",
+                        GRAY,
+                        version,
+                        format_age(age),
+                        RESET
+                    ),
+                    None => format!(" {}[{} held back]{}", GRAY, version, RESET),
+                })
+                .unwrap_or_default();
+            let deprecated = result
+                .deprecated
+                .as_ref()
+                .map(|message| format!(" {}[deprecated: {}]{}", MAJOR, message, RESET))
+                .unwrap_or_default();
+            let (new, aligned) = match &result.aligned {
+                Some(aligned) => (aligned.clone(), format!(" {}[aligned]{}", GRAY, RESET)),
+                None => (
+                    packages::colorize_version(
+                        result
+                            .installed
+                            .as_deref()
+                            .filter(|_| args.installed)
+                            .unwrap_or(&result.current),
+                        result.target.as_deref().unwrap_or_default(),
+                    ),
+                    String::new(),
+                ),
+            };
+            let installed = result
+                .installed
+                .as_ref()
+                .filter(|installed| {
+                    floor_version(&result.current)
+                        .is_none_or(|floor| floor.to_string() != **installed)
+                })
+                .map(|installed| format!(" {}({} installed){}", GRAY, installed, RESET))
+                .unwrap_or_default();
+            items.push(format!(
+                "{}: {}{} -> {} ({}){}{}{}",
+                result.name,
+                result.current,
+                installed,
+                new,
+                result.dependency_type.name(),
+                aligned,
+                held_back,
+                deprecated
+            ));
+        }
+        items
+    };
 
-    if !args.interactive && !args.update {
-        let items = generate_items(&to_update, &mut json_data);
-        for item in items {
+    // Prints the items under a header for each workspace member
+    let print_items = |to_update: &[&mut DependencyResult]| {
+        let mut workspace = None;
+        for (result, item) in to_update.iter().zip(generate_items(to_update)) {
+            if result.workspace.is_some() && result.workspace != workspace {
+                workspace = result.workspace.clone();
+                println!("\n{}", workspace.as_deref().unwrap_or_default());
+            }
             println!("{}", item);
         }
+    };
+
+    if list_only || (!can_prompt && !args.update) {
+        print_items(&to_update);
+        if args.check {
+            process::exit(exit_code);
+        }
+        if list_only {
+            return;
+        }
+        println!(
+            "{}Not running in a terminal, so nothing was updated. Pass -u to update.{}",
+            GRAY, RESET
+        );
+        return;
+    }
+
+    if !interactive && !args.update {
+        print_items(&to_update);
 
         let user_input = prompt_confirm(
             "\nDo you want to update all of these packages? (y/N)",
@@ -222,35 +621,41 @@ async fn main() {
             return;
         }
 
-        for &(ref package, ref version, ref is_dev, ref is_peer) in &to_update {
-            set_new_package_version(&package, &version, *is_dev, *is_peer, &mut json_data);
+        for result in to_update.iter_mut() {
+            apply_update(
+                result,
+                args.range_policy,
+                &mut manifests[result.manifest].json,
+            );
         }
-        let new_json = serde_json::to_string_pretty(&json_data).unwrap();
-        fs::write(&path, new_json).expect("Unable to write file");
+        write_changed(&manifests).expect("Unable to write file");
 
         println!("Updated {} packages.", to_update.len());
+        install();
     }
 
-    if args.interactive && args.update {
+    if interactive && args.update {
         println!(
             "{}You're using both interactive and update flags. Continuing with interactive mode.{}",
             GRAY, RESET
         );
     }
 
-    if args.interactive {
+    if interactive {
         let mut selected = vec![];
-        let items = generate_items(&to_update, &json_data);
+        let items = generate_items(&to_update)
+            .into_iter()
+            .zip(&to_update)
+            .map(|(item, result)| match &result.workspace {
+                Some(workspace) => format!("{}{}{} {}", GRAY, workspace, RESET, item),
+                None => item,
+            })
+            .collect::<Vec<_>>();
 
         let selections = MultiSelect::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!("Select packages to update {}(space to select, enter to confirm, arrow keys to navigate, a to toggle all)\x1b[0m", GRAY.to_string()))
+            .with_prompt(format!("Select packages to update {}(space to select, enter to confirm, arrow keys to navigate, a to toggle all)\x1b[0m", GRAY))
             .items(&items)
-            .defaults(
-                (0..items.len())
-                    .map(|_| true)
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            )
+            .defaults(vec![true; items.len()].as_slice())
             .interact()
             .expect("Failed to read user input");
 
@@ -263,49 +668,55 @@ async fn main() {
             return;
         }
 
-        for (i, &(ref package, ref version, ref is_dev, ref is_peer)) in
-            to_update.iter().enumerate()
-        {
-            if selected.contains(&i) {
-                set_new_package_version(&package, &version, *is_dev, *is_peer, &mut json_data);
+        // Aligned ranges are one update, so picking any member picks all of them
+        let aligned = selected
+            .iter()
+            .filter(|i| to_update[**i].aligned.is_some())
+            .map(|i| to_update[*i].name.clone())
+            .collect::<HashSet<_>>();
+        let mut updated = 0;
+        for (i, result) in to_update.iter_mut().enumerate() {
+            if selected.contains(&i) || (result.aligned.is_some() && aligned.contains(&result.name))
+            {
+                apply_update(
+                    result,
+                    args.range_policy,
+                    &mut manifests[result.manifest].json,
+                );
+                updated += 1;
             }
         }
 
-        let new_json = serde_json::to_string_pretty(&json_data).unwrap();
-        fs::write(&path, new_json).expect("Unable to write file");
-        println!("Updated {} package(s)", selected.len());
+        write_changed(&manifests).expect("Unable to write file");
+        println!("Updated {} package(s)", updated);
+        install();
         return;
     }
 
     if args.update {
-        update_dependencies(&mut json_data, DEPENDENCIES, &to_update);
-        update_dependencies(&mut json_data, DEV_DEPENDENCIES, &to_update);
-        update_dependencies(&mut json_data, PEER_DEPENDENCIES, &to_update);
+        for result in to_update.iter_mut() {
+            apply_update(
+                result,
+                args.range_policy,
+                &mut manifests[result.manifest].json,
+            );
+        }
 
-        let new_json = serde_json::to_string_pretty(&json_data).unwrap();
-        fs::write(&path, new_json).expect("Unable to write file");
+        write_changed(&manifests).expect("Unable to write file");
         println!(
             "Updated {} package(s) in {}ms.",
             to_update.len(),
             current_time.elapsed().as_millis()
         );
+        install();
     }
 }
 
-fn update_dependencies(
-    json_data: &mut Value,
-    key: &str,
-    to_update: &[(String, String, bool, bool)],
-) {
-    if let Some(deps) = json_data.get_mut(key) {
-        for &(ref package, ref version, is_dev, is_peer) in to_update {
-            if (key == DEPENDENCIES && !is_dev && !is_peer)
-                || (key == DEV_DEPENDENCIES && is_dev)
-                || (key == PEER_DEPENDENCIES && is_peer)
-            {
-                deps[package] = Value::String(version.clone());
-            }
-        }
-
-    }
-}
\ No newline at end of file
+// Function to name the workspace member a result belongs to, eg. " in @acme/ui"
+fn in_workspace(result: &DependencyResult) -> String {
+    result
+        .workspace
+        .as_ref()
+        .map(|workspace| format!(" in {}", workspace))
+        .unwrap_or_default()
+}
@@ -0,0 +1,407 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::{Serializer, Value};
+
+use crate::catalog::{patch_pnpm_catalogs, read_pnpm_catalogs};
+use crate::workspace::PNPM_WORKSPACE_FILE;
+
+pub const MANIFEST_FILE: &str = "package.json";
+
+// Function to turn a user supplied path into a package.json path.
+// Accepts either a directory containing package.json or the file itself
+pub fn resolve_manifest_path(path: &Path) -> Option<PathBuf> {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+
+    let manifest = if path.is_dir() {
+        path.join(MANIFEST_FILE)
+    } else {
+        path.to_path_buf()
+    };
+
+    manifest.is_file().then_some(manifest)
+}
+
+// Function to get the directory a package.json lives in
+pub fn manifest_dir(manifest: &Path) -> PathBuf {
+    match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// A package.json being checked, either the project itself or a workspace member.
+// pnpm-workspace.yaml is one too when it has catalogs, read as just those
+pub struct Manifest {
+    pub path: PathBuf,
+    // The text as read, so formatting can be kept when writing
+    pub content: String,
+    pub json: Value,
+    // Name shown above the member's results, None outside of workspace mode
+    pub label: Option<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let json = serde_json::from_str(&content)
+            .map_err(|e| format!("Unable to parse {}: {}", path.display(), e))?;
+        Ok(Manifest {
+            path: path.to_path_buf(),
+            content,
+            json,
+            label: None,
+        })
+    }
+
+    pub fn load_pnpm_workspace(path: &Path) -> Result<Manifest, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        Ok(Manifest {
+            path: path.to_path_buf(),
+            json: read_pnpm_catalogs(&content),
+            content,
+            label: None,
+        })
+    }
+
+    fn is_pnpm_workspace(&self) -> bool {
+        self.path
+            .file_name()
+            .is_some_and(|name| name == PNPM_WORKSPACE_FILE)
+    }
+
+    // The "name" field, falling back to the directory relative to the workspace root
+    pub fn name_or(&self, relative_dir: &str) -> String {
+        self.json["name"]
+            .as_str()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| relative_dir.to_string())
+    }
+
+    // Whether an update was applied since the file was read
+    pub fn is_changed(&self) -> bool {
+        if self.is_pnpm_workspace() {
+            return read_pnpm_catalogs(&self.content) != self.json;
+        }
+        serde_json::from_str::<Value>(&self.content).is_ok_and(|original| original != self.json)
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        if self.is_pnpm_workspace() {
+            return fs::write(&self.path, patch_pnpm_catalogs(&self.content, &self.json));
+        }
+        write_manifest(&self.path, &self.content, &self.json)
+    }
+}
+
+// Function to write every manifest that had updates applied, leaving the rest untouched
+pub fn write_changed(manifests: &[Manifest]) -> io::Result<()> {
+    for manifest in manifests.iter().filter(|manifest| manifest.is_changed()) {
+        manifest.write()?;
+    }
+    Ok(())
+}
+
+// Function to write package.json back to disk while keeping its formatting.
+// Only the string values that changed are replaced in the original text, so key order,
+// indentation, line endings and the final newline stay exactly as they were
+pub fn write_manifest(path: &Path, original: &str, updated: &Value) -> io::Result<()> {
+    fs::write(path, patch_manifest(original, updated))
+}
+
+pub fn patch_manifest(original: &str, updated: &Value) -> String {
+    let Ok(original_json) = serde_json::from_str::<Value>(original) else {
+        return reformat(original, updated);
+    };
+
+    let mut changes = vec![];
+    if !collect_changes(&original_json, updated, &mut vec![], &mut changes) {
+        return reformat(original, updated);
+    }
+
+    let mut edits = vec![];
+    for (path, value) in changes {
+        let path = path.iter().map(|key| key.as_str()).collect::<Vec<_>>();
+        match find_string_value(original, &path) {
+            Some(range) => edits.push((range, value)),
+            None => return reformat(original, updated),
+        }
+    }
+
+    // Apply from the back so earlier ranges stay valid
+    edits.sort_by_key(|(range, _)| Reverse(range.start));
+    let mut patched = original.to_string();
+    for (range, value) in edits {
+        patched.replace_range(range, &serde_json::to_string(&value).unwrap());
+    }
+    patched
+}
+
+// Function to collect the key paths of string values that differ between two documents.
+// Returns false if anything other than a string value changed
+fn collect_changes(
+    original: &Value,
+    updated: &Value,
+    path: &mut Vec<String>,
+    changes: &mut Vec<(Vec<String>, String)>,
+) -> bool {
+    match (original, updated) {
+        (Value::Object(original), Value::Object(updated)) => {
+            if original.len() != updated.len() {
+                return false;
+            }
+            for (key, updated_value) in updated {
+                let Some(original_value) = original.get(key) else {
+                    return false;
+                };
+                path.push(key.clone());
+                let unchanged = collect_changes(original_value, updated_value, path, changes);
+                path.pop();
+                if !unchanged {
+                    return false;
+                }
+            }
+            true
+        }
+        (Value::String(original), Value::String(updated)) => {
+            if original != updated {
+                changes.push((path.clone(), updated.clone()));
+            }
+            true
+        }
+        (original, updated) => original == updated,
+    }
+}
+
+// Function to reserialize the whole document using the original indentation,
+// line endings and final newline. Used when an in place edit isn't possible
+fn reformat(original: &str, updated: &Value) -> String {
+    let indent = detect_indent(original);
+    let mut buffer = vec![];
+    let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = Serializer::with_formatter(&mut buffer, formatter);
+    updated.serialize(&mut serializer).unwrap();
+
+    let mut formatted = String::from_utf8(buffer).unwrap();
+    if original.contains("\r\n") {
+        formatted = formatted.replace('\n', "\r\n");
+    }
+    if original.ends_with("\r\n") {
+        formatted.push_str("\r\n");
+    } else if original.ends_with('\n') {
+        formatted.push('\n');
+    }
+    formatted
+}
+
+// Function to find the indentation of the first indented line, defaulting to two spaces
+fn detect_indent(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start_matches([' ', '\t']);
+            &line[..line.len() - trimmed.len()]
+        })
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+        .to_string()
+}
+
+// Function to find the byte range (including quotes) of a string value at a key path
+fn find_string_value(content: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut scanner = Scanner {
+        bytes: content.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_whitespace();
+    scanner.find(path)
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn find(&mut self, path: &[&str]) -> Option<Range<usize>> {
+        self.expect(b'{')?;
+        loop {
+            self.skip_whitespace();
+            if self.peek()? == b'}' {
+                return None;
+            }
+
+            let key = self.string()?;
+            let key: String = serde_json::from_slice(&self.bytes[key]).ok()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+
+            if key == path[0] {
+                if path.len() == 1 {
+                    return (self.peek()? == b'"').then(|| self.string())?;
+                }
+                return self.find(&path[1..]);
+            }
+
+            self.skip_value()?;
+            self.skip_whitespace();
+            if self.peek()? == b',' {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.peek()? != byte {
+            return None;
+        }
+        self.pos += 1;
+        Some(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(start..self.pos);
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                return Some(());
+                            }
+                        }
+                        _ => (),
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.peek()?,
+                    b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r'
+                ) {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Function to patch a manifest after changing the string at a key path
+    fn patch(original: &str, path: &[&str], version: &str) -> String {
+        let mut updated = serde_json::from_str::<Value>(original).unwrap();
+        let value = path
+            .iter()
+            .fold(&mut updated, |value, key| &mut value[*key]);
+        *value = Value::String(version.to_string());
+        patch_manifest(original, &updated)
+    }
+
+    #[test]
+    fn keeps_tabs_and_crlf_when_patching() {
+        let original = "{\r\n\t\"name\": \"app\",\r\n\t\"dependencies\": {\r\n\t\t\"react\": \"^18.2.0\",\r\n\t\t\"vue\":\"^3.0.0\"\r\n\t}\r\n}";
+        assert_eq!(
+            patch(original, &["dependencies", "react"], "^18.3.1"),
+            original.replace("^18.2.0", "^18.3.1")
+        );
+        assert_eq!(
+            patch(original, &["dependencies", "vue"], "^3.4.0"),
+            original.replace("^3.0.0", "^3.4.0")
+        );
+    }
+
+    #[test]
+    fn finds_keys_with_escaped_quotes() {
+        let original = r#"{
+  "description": "see \"dependencies\": {\"react\": \"1.0.0\"}",
+  "overrides": { "react": "1.0.0", "list": ["}", "]", {"react": "1.0.0"}] },
+  "dependencies": {
+    "we\"ird": "1.0.0",
+    "react": "1.0.0"
+  }
+}
+"#;
+        assert_eq!(
+            patch(original, &["dependencies", "react"], "2.0.0"),
+            original.replace("\"react\": \"1.0.0\"\n", "\"react\": \"2.0.0\"\n")
+        );
+        assert_eq!(
+            patch(original, &["dependencies", "we\"ird"], "2.0.0"),
+            original.replace("\"we\\\"ird\": \"1.0.0\"", "\"we\\\"ird\": \"2.0.0\"")
+        );
+    }
+
+    #[test]
+    fn reformats_with_the_original_layout_when_keys_change() {
+        let original = "{\r\n\t\"dependencies\": {\r\n\t\t\"react\": \"^18.2.0\"\r\n\t}\r\n}\r\n";
+        let updated = json!({"dependencies": {"react": "^18.3.1", "vue": "^3.4.0"}});
+        assert_eq!(
+            patch_manifest(original, &updated),
+            "{\r\n\t\"dependencies\": {\r\n\t\t\"react\": \"^18.3.1\",\r\n\t\t\"vue\": \"^3.4.0\"\r\n\t}\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn only_finds_string_values() {
+        let content = r#"{"a": {"b": 1, "c": [1, 2], "d": "x\\"}, "e": true}"#;
+        assert_eq!(find_string_value(content, &["a", "b"]), None);
+        assert_eq!(find_string_value(content, &["a", "c"]), None);
+        assert_eq!(find_string_value(content, &["e"]), None);
+        assert_eq!(find_string_value(content, &["missing"]), None);
+        let range = find_string_value(content, &["a", "d"]).unwrap();
+        assert_eq!(&content[range], r#""x\\""#);
+    }
+}
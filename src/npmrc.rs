@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
+
+// Credentials sent along with registry requests
+#[derive(Debug, Clone, PartialEq)]
+pub enum Auth {
+    Bearer(String),
+    Basic(String),
+}
+
+impl Auth {
+    // Value for the Authorization header
+    pub fn header_value(&self) -> String {
+        match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic(credentials) => format!("Basic {}", credentials),
+        }
+    }
+}
+
+// Registry settings resolved from the CLI, the environment and .npmrc files
+#[derive(Debug)]
+pub struct Npmrc {
+    pub registry: String,
+    pub scopes: HashMap<String, String>,
+    settings: HashMap<String, String>,
+}
+
+impl Npmrc {
+    // Merges settings in the same order npm does, where later sources win:
+    // global npmrc, user .npmrc, project .npmrc, NPM_CONFIG_REGISTRY, --registry
+    pub fn load(project_dir: &Path, cli_registry: Option<&str>) -> Npmrc {
+        let mut settings = HashMap::new();
+        for file in config_files(project_dir).iter().rev() {
+            if let Ok(content) = fs::read_to_string(file) {
+                settings.extend(parse_lines(&content));
+            }
+        }
+
+        if let Some(registry) = env_config("registry") {
+            settings.insert("registry".to_string(), registry);
+        }
+        if let Some(registry) = cli_registry {
+            settings.insert("registry".to_string(), registry.to_string());
+        }
+
+        let registry = settings
+            .get("registry")
+            .map(|registry| normalize_registry(registry))
+            .unwrap_or_else(|| DEFAULT_REGISTRY.to_string());
+
+        let scopes = settings
+            .iter()
+            .filter_map(|(key, value)| {
+                let scope = key.strip_suffix(":registry")?;
+                scope
+                    .starts_with('@')
+                    .then(|| (scope.to_string(), normalize_registry(value)))
+            })
+            .collect();
+
+        Npmrc {
+            registry,
+            scopes,
+            settings,
+        }
+    }
+
+    // Function to pick the registry a package should be fetched from
+    pub fn registry_for(&self, package: &str) -> &str {
+        package
+            .split_once('/')
+            .filter(|(scope, _)| scope.starts_with('@'))
+            .and_then(|(scope, _)| self.scopes.get(scope))
+            .unwrap_or(&self.registry)
+    }
+
+    // Function to find the credentials configured for a registry URL.
+    // Walks up the URL path, so //host/path/:_authToken also covers //host/path/sub/
+    pub fn auth_for(&self, url: &str) -> Option<Auth> {
+        let mut nerf_dart = nerf_dart(url)?;
+
+        loop {
+            if let Some(auth) = self.auth_at(&nerf_dart) {
+                return Some(auth);
+            }
+
+            let parent = nerf_dart
+                .trim_end_matches('/')
+                .rsplit_once('/')
+                .map(|(parent, _)| format!("{}/", parent))?;
+            if parent.len() <= 2 {
+                break;
+            }
+            nerf_dart = parent;
+        }
+
+        // Legacy top level credentials only ever apply to the default registry
+        if nerf_dart_matches(url, &self.registry) {
+            return self.auth_at("");
+        }
+        None
+    }
+
+    fn auth_at(&self, prefix: &str) -> Option<Auth> {
+        let get = |key: &str| self.settings.get(&format!("{}{}", prefix, key));
+        let separator = if prefix.is_empty() { "" } else { ":" };
+
+        if let Some(token) = get(&format!("{}_authToken", separator)) {
+            return Some(Auth::Bearer(token.clone()));
+        }
+        if let Some(auth) = get(&format!("{}_auth", separator)) {
+            return Some(Auth::Basic(auth.clone()));
+        }
+        if let (Some(username), Some(password)) = (
+            get(&format!("{}username", separator)),
+            get(&format!("{}_password", separator)),
+        ) {
+            // _password is stored base64 encoded in .npmrc
+            let password = STANDARD
+                .decode(password)
+                .ok()
+                .and_then(|password| String::from_utf8(password).ok())?;
+            return Some(Auth::Basic(
+                STANDARD.encode(format!("{}:{}", username, password)),
+            ));
+        }
+        None
+    }
+}
+
+// Function to read an npm setting from the environment, eg. NPM_CONFIG_REGISTRY
+fn env_config(key: &str) -> Option<String> {
+    let upper = format!("NPM_CONFIG_{}", key.to_uppercase());
+    let lower = format!("npm_config_{}", key);
+    env::var(upper)
+        .or_else(|_| env::var(lower))
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+// Function to list .npmrc files from highest to lowest priority
+fn config_files(project_dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![project_dir.join(".npmrc")];
+
+    if let Some(user_config) = env_config("userconfig") {
+        files.push(PathBuf::from(user_config));
+    } else if let Some(home) = home_dir() {
+        files.push(home.join(".npmrc"));
+    }
+
+    if let Some(global_config) = env_config("globalconfig") {
+        files.push(PathBuf::from(global_config));
+    } else if let Some(prefix) = env_config("prefix").or_else(|| env::var("PREFIX").ok()) {
+        files.push(Path::new(&prefix).join("etc").join("npmrc"));
+    }
+
+    files
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+// Function to parse the key=value lines of an .npmrc file, ignoring comments
+fn parse_lines(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (expand_env(key.trim()), expand_env(unquote(value.trim()))))
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+}
+
+// Function to replace ${VAR} with its environment value. \${VAR} is kept as is,
+// and so is any variable that isn't set, which makes a broken token easy to spot
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let end = start + end;
+
+        if rest[..start].ends_with('\\') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str(&rest[start..=end]);
+        } else {
+            expanded.push_str(&rest[..start]);
+            match env::var(&rest[start + 2..end]) {
+                Ok(variable) => expanded.push_str(&variable),
+                Err(_) => expanded.push_str(&rest[start..=end]),
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+// Function to make sure the registry URL always ends with a slash
+fn normalize_registry(registry: &str) -> String {
+    let registry = registry.trim();
+    if registry.ends_with('/') {
+        registry.to_string()
+    } else {
+        format!("{}/", registry)
+    }
+}
+
+// Function to turn a URL into the //host/path/ form npm uses as a credentials key
+fn nerf_dart(url: &str) -> Option<String> {
+    let without_protocol = url.split_once("://").map(|(_, rest)| rest)?;
+    let without_query = without_protocol
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(without_protocol);
+    let directory = match without_query.rfind('/') {
+        Some(index) => &without_query[..=index],
+        None => return Some(format!("//{}/", without_query)),
+    };
+    Some(format!("//{}", directory))
+}
+
+fn nerf_dart_matches(url: &str, registry: &str) -> bool {
+    match (nerf_dart(url), nerf_dart(registry)) {
+        (Some(url), Some(registry)) => url.starts_with(&registry),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Function to write an .npmrc into a fresh directory under the system temp dir
+    fn project(name: &str, npmrc: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("nrush-npmrc-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".npmrc"), npmrc).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_registries_from_the_project_npmrc() {
+        let dir = project(
+            "registries",
+            "# comment\n; also a comment\n@corp:registry = \"https://npm.corp.example/\"\n@other:registry=https://other.example/npm\n",
+        );
+        let npmrc = Npmrc::load(&dir, Some("https://mirror.example"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(npmrc.registry, "https://mirror.example/");
+        assert_eq!(npmrc.registry_for("react"), "https://mirror.example/");
+        assert_eq!(npmrc.registry_for("@corp/ui"), "https://npm.corp.example/");
+        assert_eq!(
+            npmrc.registry_for("@other/ui"),
+            "https://other.example/npm/"
+        );
+        assert_eq!(npmrc.registry_for("@unknown/ui"), "https://mirror.example/");
+    }
+
+    #[test]
+    fn finds_credentials_for_a_registry_path() {
+        let dir = project(
+            "auth",
+            "//npm.corp.example/:_authToken=corp-token\n\
+             //other.example/npm/:_auth=b3RoZXI6cGFzcw==\n\
+             //basic.example/:username=user\n\
+             //basic.example/:_password=cGFzcw==\n\
+             _authToken=legacy-token\n",
+        );
+        let npmrc = Npmrc::load(&dir, Some("https://legacy.example/"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            npmrc.auth_for("https://npm.corp.example/@corp%2fui"),
+            Some(Auth::Bearer("corp-token".to_string()))
+        );
+        assert_eq!(
+            npmrc.auth_for("https://other.example/npm/sub/react"),
+            Some(Auth::Basic("b3RoZXI6cGFzcw==".to_string()))
+        );
+        assert_eq!(
+            npmrc.auth_for("https://basic.example/react"),
+            Some(Auth::Basic(STANDARD.encode("user:pass")))
+        );
+        assert_eq!(
+            npmrc.auth_for("https://legacy.example/react"),
+            Some(Auth::Bearer("legacy-token".to_string()))
+        );
+        assert_eq!(npmrc.auth_for("https://other.example/react"), None);
+    }
+
+    #[test]
+    fn expands_environment_variables() {
+        env::set_var("NRUSH_TEST_TOKEN", "secret");
+        env::remove_var("NRUSH_TEST_UNSET");
+
+        assert_eq!(expand_env("${NRUSH_TEST_TOKEN}"), "secret");
+        assert_eq!(expand_env("a-${NRUSH_TEST_TOKEN}-b"), "a-secret-b");
+        assert_eq!(expand_env("${NRUSH_TEST_UNSET}"), "${NRUSH_TEST_UNSET}");
+        assert_eq!(expand_env("\\${NRUSH_TEST_TOKEN}"), "${NRUSH_TEST_TOKEN}");
+        assert_eq!(expand_env("${NRUSH_TEST_TOKEN"), "${NRUSH_TEST_TOKEN");
+
+        let dir = project(
+            "env",
+            "//npm.corp.example/:_authToken=${NRUSH_TEST_TOKEN}\n",
+        );
+        let npmrc = Npmrc::load(&dir, None);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            npmrc.auth_for("https://npm.corp.example/react"),
+            Some(Auth::Bearer("secret".to_string()))
+        );
+    }
+}
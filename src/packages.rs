@@ -1,152 +1,298 @@
+use crate::arguments::RangePolicy;
+use crate::cache::now;
+use crate::client::{FetchError, RegistryClient};
 use crate::constants::{MAJOR, MINOR, PATCH, RESET};
-use reqwest::{get, Error};
+use crate::helpers::parse_timestamp;
+use crate::range::{rewrite, Range};
+use crate::report::{Bump, DependencyResult};
+use crate::specifier::{classify, version_range, Specifier};
 use semver::Version;
+use serde::{Deserialize, Deserializer};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
-// Function to fetch package version asynchronously
-pub async fn fetch_package_version(package: String) -> Result<(String, String), Error> {
-    let npm_url = format!("https://registry.npmjs.org/{}/latest", &package);
-    let response = get(&npm_url).await?;
-    let json = response.json::<Value>().await?;
-    let latest_version = json["version"]
-        .as_str()
-        .unwrap_or("Version not found")
-        .to_string();
-    Ok((package, latest_version))
+// The registry document describing every published version of a package
+#[derive(Debug, Deserialize)]
+pub struct Packument {
+    #[serde(rename = "dist-tags", default)]
+    pub dist_tags: HashMap<String, String>,
+    #[serde(default)]
+    pub versions: BTreeMap<String, VersionMetadata>,
+    // Publish time per version, only in the full document
+    #[serde(default)]
+    pub time: HashMap<String, String>,
+    // How old the cached copy is when it was used without asking the registry
+    #[serde(skip)]
+    pub cache_age: Option<Duration>,
 }
 
-// Function to normalize version strings
-pub fn normalize_version(version: &str) -> String {
-    version
-        .chars()
-        .filter(|&c| c.is_ascii_digit() || c == '.')
-        .collect()
+// Metadata of a single published version
+#[derive(Debug, Deserialize)]
+pub struct VersionMetadata {
+    // Why the version shouldn't be used anymore, set with `npm deprecate`
+    #[serde(default, deserialize_with = "deprecation_message")]
+    pub deprecated: Option<String>,
 }
 
-// Function to return ranges of the version
-pub fn get_version_range(version: &str) -> String {
-    if version == "*" {
-        return "".to_string();
+// Function to read the deprecated field. It's normally the message, but some old
+// packages have a boolean, and `npm deprecate pkg ""` leaves an empty string behind
+fn deprecation_message<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(message) if !message.trim().is_empty() => Some(message.trim().to_string()),
+        Value::Bool(true) => Some("No reason given".to_string()),
+        _ => None,
+    })
+}
+
+impl Packument {
+    // Function to get every published version that is valid semver
+    pub fn parsed_versions(&self) -> Vec<Version> {
+        self.versions
+            .keys()
+            .filter_map(|version| Version::parse(version).ok())
+            .collect()
+    }
+
+    // Function to get the deprecation message of a version, if it's deprecated
+    pub fn deprecation(&self, version: &str) -> Option<&str> {
+        self.versions.get(version)?.deprecated.as_deref()
+    }
+
+    // Function to get how long ago a version was published, if the document has its time
+    pub fn age(&self, version: &str) -> Option<Duration> {
+        let published = parse_timestamp(self.time.get(version)?)?;
+        Some(Duration::from_secs(now().saturating_sub(published)))
     }
-    version
-        .chars()
-        .filter(|&c| !c.is_ascii_digit() && c != '.')
-        .collect()
 }
 
-// Function to colorize version strings
-pub fn colorize_version(current_version: &str, latest_version: &str) -> String {
-    let current_version = normalize_version(current_version);
-    let latest_version = normalize_version(latest_version);
+// Function to fetch the package document (packument) asynchronously, by registry name.
+// The abbreviated document is used unless `full` is set, which is only needed for
+// fields like `time` that the abbreviated one leaves out
+pub async fn fetch_packument(
+    name: String,
+    full: bool,
+    client: &RegistryClient,
+) -> (String, Result<Packument, FetchError>) {
+    let npm_url = packument_url(client.npmrc.registry_for(&name), &name);
+    let packument = async {
+        let response = client.get(&npm_url, full).await?;
+        let mut packument = serde_json::from_str::<Packument>(&response.body)?;
+        packument.cache_age = response.cache_age;
+        Ok::<_, FetchError>(packument)
+    };
+    (name, packument.await)
+}
 
-    let current_version = if current_version == "" {
-        Version::parse("0.0.0").unwrap()
-    } else {
-        Version::parse(&current_version).unwrap()
+// Function to list the dependencies of a section that can be looked up in the registry,
+// as (dependency name, registry name) pairs. Everything else is added to `skipped`
+pub fn registry_dependencies(
+    json_data: &Value,
+    dependency_type: &str,
+    skipped: &mut Vec<(String, &'static str)>,
+) -> Vec<(String, String)> {
+    let Some(dependencies) = json_data[dependency_type].as_object() else {
+        return Vec::new(); // Return empty vector if no dependencies are found
     };
 
-    let latest_version = Version::parse(&latest_version).unwrap();
+    let mut packages = vec![];
+    for (package, spec) in dependencies {
+        match classify(spec.as_str().unwrap_or("")) {
+            Specifier::Range(_) => packages.push((package.clone(), package.clone())),
+            Specifier::Alias { name, .. } => packages.push((package.clone(), name.to_string())),
+            Specifier::Skip(reason) => skipped.push((package.clone(), reason)),
+        }
+    }
+    packages
+}
+
+// Function to build the packument URL, scoped names keep their @ but escape the slash
+pub fn packument_url(registry: &str, package: &str) -> String {
+    format!("{}{}", registry, package.replace('/', "%2f"))
+}
+
+// Function to check if a new version is an update for the current range.
+// The floor of the range has to be lower than the new version, the rewritten spec
+// can't have a lower floor and has to allow the new version, and "*" is only updated
+// if --update-any is passed.
+// For "a || b" the floor is the one of the alternative that gets rewritten
+pub fn is_update(
+    current_version: &str,
+    new_version: &str,
+    update_any: bool,
+    policy: RangePolicy,
+) -> bool {
+    let range = version_range(current_version).and_then(Range::parse);
+    let (Some(range), Ok(parsed)) = (range, Version::parse(new_version)) else {
+        return false;
+    };
+    if range.is_any() {
+        return update_any;
+    }
+
+    let Some(floor) = range.highest_floor() else {
+        return false;
+    };
+    let new_spec = new_version_spec(current_version, new_version, policy);
+    parsed > floor
+        && new_spec != current_version
+        && floor_version(&new_spec).is_some_and(|new_floor| new_floor >= floor)
+        && version_range(&new_spec)
+            .and_then(Range::parse)
+            .is_some_and(|new_range| new_range.satisfies(&parsed))
+}
+
+// Function to build the spec written to package.json for a new version.
+// Preserve keeps the operators of the current range, even compound ones,
+// while the other policies replace the whole range
+pub fn new_version_spec(current_version: &str, new_version: &str, policy: RangePolicy) -> String {
+    let Ok(parsed) = Version::parse(new_version) else {
+        return new_version.to_string();
+    };
+
+    // Aliases keep their npm:name@ prefix and only have the range part rewritten
+    if let Specifier::Alias { name, range } = classify(current_version) {
+        return format!(
+            "npm:{}@{}",
+            name,
+            new_version_spec(range, new_version, policy)
+        );
+    }
+
+    match policy {
+        RangePolicy::Preserve => {
+            rewrite(current_version, &parsed).unwrap_or(new_version.to_string())
+        }
+        RangePolicy::Pin => new_version.to_string(),
+        RangePolicy::Caret => format!("^{}", new_version),
+        RangePolicy::Tilde => format!("~{}", new_version),
+    }
+}
+
+// Function to get the lowest version a range allows, eg. 1.2.3 for ^1.2.3. For "a || b"
+// it's the floor of the alternative an update rewrites, so bumps are measured from it
+pub fn floor_version(version: &str) -> Option<Version> {
+    Range::parse(version_range(version)?)?.highest_floor()
+}
+
+// Function to tell how big of a jump a new version is from the current range,
+// None if it isn't higher than the floor of the range. Only a move to another
+// prerelease is a prerelease bump, eg. 2.0.0-rc.3 to 2.0.0 is a patch bump
+pub fn bump_kind(current_version: &str, new_version: &str) -> Option<Bump> {
+    let current = floor_version(current_version).unwrap_or(Version::new(0, 0, 0));
+    let new = Version::parse(new_version).ok()?;
 
-    if current_version.major < latest_version.major {
-        format!(
-            "{}{}.{}.{} ({}){}",
-            MAJOR, latest_version.major, latest_version.minor, latest_version.patch, "major", RESET
-        )
-    } else if current_version.minor < latest_version.minor {
-        format!(
-            "{}.{}{}.{} ({}){}",
+    if new <= current {
+        None
+    } else if new.major != current.major {
+        Some(Bump::Major)
+    } else if new.minor != current.minor {
+        Some(Bump::Minor)
+    } else if new.patch != current.patch || new.pre.is_empty() {
+        Some(Bump::Patch)
+    } else {
+        Some(Bump::Prerelease)
+    }
+}
+
+// Function to colorize version strings. Prereleases keep their tag, and a move
+// between builds of the same version, eg. 2.0.0-rc.3 to 2.0.0-rc.4, is a prerelease bump
+pub fn colorize_version(current_version: &str, latest_version: &str) -> String {
+    let bump = bump_kind(current_version, latest_version);
+    let current_version = floor_version(current_version).unwrap_or(Version::new(0, 0, 0));
+    let latest_version = Version::parse(latest_version).unwrap();
+    let pre = if latest_version.pre.is_empty() {
+        String::new()
+    } else {
+        format!("-{}", latest_version.pre)
+    };
+
+    match bump {
+        Some(Bump::Major) => format!(
+            "{}{}.{}.{}{} ({}){}",
+            MAJOR,
+            latest_version.major,
+            latest_version.minor,
+            latest_version.patch,
+            pre,
+            "major",
+            RESET
+        ),
+        Some(Bump::Minor) => format!(
+            "{}.{}{}.{}{} ({}){}",
             current_version.major,
             MINOR,
             latest_version.minor,
             latest_version.patch,
+            pre,
             "minor",
             RESET
-        )
-    } else if current_version.patch < latest_version.patch {
-        format!(
-            "{}.{}.{}{} ({}){}",
+        ),
+        Some(Bump::Patch) => format!(
+            "{}.{}.{}{}{} ({}){}",
             current_version.major,
             current_version.minor,
             PATCH,
             latest_version.patch,
+            pre,
             "patch",
             RESET
-        )
-    } else {
-        latest_version.to_string()
-    }
-}
-
-// Determine the package type
-pub fn package_type(is_dev: &bool, is_peer: &bool) -> &'static str {
-    if *is_dev {
-        "dev"
-    } else if *is_peer {
-        "peer"
-    } else {
-        "prod"
+        ),
+        Some(Bump::Prerelease) => format!(
+            "{}.{}.{}{}{} ({}){}",
+            latest_version.major,
+            latest_version.minor,
+            latest_version.patch,
+            PATCH,
+            pre,
+            "prerelease",
+            RESET
+        ),
+        None => latest_version.to_string(),
     }
 }
 
-// Function to get the current package version
-pub fn get_current_package_version(
-    package: &str,
-    json_data: &Value,
-    dev_package_names: &Vec<String>,
-    peer_package_names: &Vec<String>,
-) -> String {
-    let get_version_from_dependency_type = |dependency_type: &str| {
-        json_data[dependency_type][package]
-            .as_str()
-            .unwrap_or("")
-            .to_string()
-    };
-
-    let get_dependency_type = |package: &str| -> &str {
-        if dev_package_names.contains(&package.to_string()) {
-            "devDependencies"
-        } else if peer_package_names.contains(&package.to_string()) {
-            "peerDependencies"
-        } else {
-            "dependencies"
-        }
-    };
-
-    let version = get_version_from_dependency_type(get_dependency_type(package));
+// Function to get the current package version from the object it's declared in
+pub fn get_current_package_version(package: &str, dependencies: &Value) -> String {
+    let version = dependencies[package].as_str().unwrap_or("");
     if version.is_empty() {
         "Version not found".to_string()
     } else {
-        version
+        version.to_string()
     }
 }
 
-// Function to set a new package version
+// Function to set a new package version in the object at the section keys
 pub fn set_new_package_version(
     package: &str,
     version: &str,
-    is_dev: bool,
-    is_peer: bool,
+    section: &[String],
+    policy: RangePolicy,
     json_data: &mut Value,
 ) {
-    let get_dependency_type = |is_dev: bool, is_peer: bool| {
-        if is_dev {
-            "devDependencies"
-        } else if is_peer {
-            "peerDependencies"
-        } else {
-            "dependencies"
-        }
-    };
-
-    let dependency_type = get_dependency_type(is_dev, is_peer);
+    let section = section
+        .iter()
+        .fold(json_data, |value, key| &mut value[key.as_str()]);
+    let current_version = section[package].as_str().unwrap_or("").to_string();
+    section[package] = Value::String(new_version_spec(&current_version, version, policy));
+}
 
-    // Fetch the current package version; handle "Version not found"
-    let current_version = get_current_package_version(&package, &json_data, &vec![], &vec![]);
-    let current_version_range = if current_version == "Version not found" {
-        "".to_string()
-    } else {
-        get_version_range(&current_version)
+// Function to write the target of an outdated dependency into package.json
+pub fn apply_update(result: &mut DependencyResult, policy: RangePolicy, json_data: &mut Value) {
+    if let Some(aligned) = &result.aligned {
+        let section = result
+            .section
+            .iter()
+            .fold(json_data, |value, key| &mut value[key.as_str()]);
+        section[&result.name] = Value::String(aligned.clone());
+        result.updated = true;
+        return;
+    }
+    let Some(version) = &result.target else {
+        return;
     };
-    let new_version = format!("{}{}", current_version_range, version);
-    json_data[dependency_type][package] = Value::String(new_version);
+    set_new_package_version(&result.name, version, &result.section, policy, json_data);
+    result.updated = true;
 }
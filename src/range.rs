@@ -0,0 +1,555 @@
+use semver::{BuildMetadata, Prerelease, Version};
+
+// A node-semver range, eg. "^1.2.3 || >=2.0.0 <2.5.0" or "1.2.3 - 1.4.0".
+// Every set is a list of comparators that all have to match, and a version
+// satisfies the range if any of the sets match
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    sets: Vec<Vec<Comparator>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+// A single term of a comparator set as it was written, before desugaring
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Simple { op: String, version: String },
+    Hyphen { from: String, to: String },
+}
+
+// A version that may be missing parts or use wildcards, eg. "1", "1.2.x" or "*"
+#[derive(Debug, Clone, PartialEq)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Prerelease,
+}
+
+impl Range {
+    pub fn parse(spec: &str) -> Option<Range> {
+        let sets = split_sets(spec)
+            .iter()
+            .map(|set| {
+                let mut comparators = vec![];
+                for term in parse_terms(set)? {
+                    comparators.extend(desugar(&term)?);
+                }
+                Some(comparators)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Range { sets })
+    }
+
+    // Whether the range matches every version, eg. "*", "x" or ""
+    pub fn is_any(&self) -> bool {
+        self.sets.iter().any(|set| set.is_empty())
+    }
+
+    // Prereleases only match when a comparator in the same set is a prerelease of
+    // the same major.minor.patch, exactly like node-semver does by default
+    pub fn satisfies(&self, version: &Version) -> bool {
+        self.matches(version, false)
+    }
+
+    // Like satisfies, but prereleases only have to match the comparators, the same
+    // as node-semver's includePrerelease option
+    pub fn satisfies_including_prerelease(&self, version: &Version) -> bool {
+        self.matches(version, true)
+    }
+
+    fn matches(&self, version: &Version, include_prerelease: bool) -> bool {
+        self.sets.iter().any(|set| {
+            set.iter().all(|comparator| comparator.matches(version))
+                && (version.pre.is_empty()
+                    || include_prerelease
+                    || set.iter().any(|comparator| {
+                        !comparator.version.pre.is_empty()
+                            && comparator.version.major == version.major
+                            && comparator.version.minor == version.minor
+                            && comparator.version.patch == version.patch
+                    }))
+        })
+    }
+
+    // Function to find the floor of the alternative rewrite() moves up, which is the
+    // one with the highest floor, eg. 18.2.0 for "^18.2.0 || ^17.0.0"
+    pub fn highest_floor(&self) -> Option<Version> {
+        let index = self.highest_set()?;
+        Range {
+            sets: vec![self.sets[index].clone()],
+        }
+        .min_version()
+    }
+
+    fn highest_set(&self) -> Option<usize> {
+        self.sets
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, set)| {
+                Range {
+                    sets: vec![(*set).clone()],
+                }
+                .min_version()
+            })
+            .map(|(index, _)| index)
+    }
+
+    // Function to find the lowest version that can possibly match the range
+    pub fn min_version(&self) -> Option<Version> {
+        for candidate in [Version::new(0, 0, 0), with_pre0(0, 0, 0)] {
+            if self.satisfies(&candidate) {
+                return Some(candidate);
+            }
+        }
+
+        self.sets
+            .iter()
+            .filter_map(|set| {
+                let mut minimum: Option<Version> = None;
+                for comparator in set {
+                    let bound = match comparator.op {
+                        Op::Gt if comparator.version.pre.is_empty() => {
+                            let mut bound = comparator.version.clone();
+                            bound.patch += 1;
+                            bound.build = BuildMetadata::EMPTY;
+                            bound
+                        }
+                        Op::Gt => {
+                            let mut bound = comparator.version.clone();
+                            bound.pre =
+                                Prerelease::new(&format!("{}.0", comparator.version.pre)).ok()?;
+                            bound
+                        }
+                        Op::Gte | Op::Eq => comparator.version.clone(),
+                        Op::Lt | Op::Lte => continue,
+                    };
+                    if minimum.as_ref().is_none_or(|minimum| bound > *minimum) {
+                        minimum = Some(bound);
+                    }
+                }
+                minimum
+            })
+            .filter(|version| self.satisfies(version))
+            .min()
+    }
+}
+
+impl Comparator {
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Eq => version.cmp_precedence(&self.version).is_eq(),
+            Op::Gt => version.cmp_precedence(&self.version).is_gt(),
+            Op::Gte => version.cmp_precedence(&self.version).is_ge(),
+            Op::Lt => version.cmp_precedence(&self.version).is_lt(),
+            Op::Lte => version.cmp_precedence(&self.version).is_le(),
+        }
+    }
+}
+
+// Function to rewrite a range so its floor moves up to a new version, keeping the
+// operators the user wrote. For "a || b" the highest alternative is rewritten, for
+// "a - b" the upper end is moved if the version is above it, and upper bounds like "<b"
+// are only moved when they exclude the version. Returns None if the spec isn't a valid range
+pub fn rewrite(spec: &str, version: &Version) -> Option<String> {
+    let range = Range::parse(spec)?;
+    if range.is_any() {
+        return Some(version.to_string());
+    }
+
+    let sets = split_sets(spec);
+    if sets.len() > 1 {
+        // Rewrite the alternative with the highest floor, keep the others as they are
+        let highest = range.highest_set()?;
+        let mut sets = sets;
+        sets[highest] = rewrite(&sets[highest], version)?;
+        return Some(sets.join(" || "));
+    }
+
+    let terms = parse_terms(&sets[0])?;
+    let rewritten = match terms.as_slice() {
+        [Term::Hyphen { from, to }] if range.satisfies(version) => format!("{} - {}", from, to),
+        [Term::Hyphen { from, .. }] => format!("{} - {}", from, version),
+        terms => terms
+            .iter()
+            .map(|term| match term {
+                Term::Simple { op, version: old } if op.starts_with('<') => {
+                    rewrite_upper(op, old, version)
+                }
+                Term::Simple { op, version: old } => Some(rewrite_simple(op, old, version)),
+                Term::Hyphen { .. } => None,
+            })
+            .collect::<Option<Vec<_>>>()?
+            .join(" "),
+    };
+    Some(rewritten)
+}
+
+// Function to move an upper bound above a version, keeping it if it already allows it
+fn rewrite_upper(op: &str, old: &str, version: &Version) -> Option<String> {
+    let upper = Range::parse(&format!("{}{}", op, old))?;
+    Some(if upper.satisfies(version) {
+        format!("{}{}", op, old)
+    } else if op == "<" {
+        format!("<{}.0.0", version.major + 1)
+    } else {
+        format!("<={}", version)
+    })
+}
+
+fn rewrite_simple(op: &str, old: &str, version: &Version) -> String {
+    let op = if op == ">" { ">=" } else { op };
+    let old = old.trim_start_matches(['v', 'V']);
+    let Some(partial) = parse_partial(old) else {
+        return format!("{}{}", op, version);
+    };
+
+    // Keep the precision of x-ranges, eg. "1.x" becomes "2.x" and "1.2" becomes "2.3"
+    let wildcard = old
+        .split('.')
+        .find(|part| matches!(*part, "x" | "X" | "*"))
+        .unwrap_or("x");
+    let new_version = match (partial.minor, partial.patch) {
+        (None, _) if old.contains('.') => format!("{}.{}", version.major, wildcard),
+        (None, _) => version.major.to_string(),
+        (Some(_), None) if old.matches('.').count() > 1 => {
+            format!("{}.{}.{}", version.major, version.minor, wildcard)
+        }
+        (Some(_), None) => format!("{}.{}", version.major, version.minor),
+        _ => version.to_string(),
+    };
+    format!("{}{}", op, new_version)
+}
+
+fn split_sets(spec: &str) -> Vec<String> {
+    spec.split("||").map(|set| set.trim().to_string()).collect()
+}
+
+// Function to split a comparator set into terms, joining operators that are
+// separated from their version by whitespace, eg. ">= 1.2.3"
+fn parse_terms(set: &str) -> Option<Vec<Term>> {
+    let words = set.split_whitespace().collect::<Vec<_>>();
+
+    if let [from, "-", to] = words.as_slice() {
+        return Some(vec![Term::Hyphen {
+            from: from.to_string(),
+            to: to.to_string(),
+        }]);
+    }
+
+    let mut terms = vec![];
+    let mut pending_op: Option<&str> = None;
+    for word in words {
+        let op_len = word
+            .find(|c: char| !matches!(c, '<' | '>' | '=' | '^' | '~'))
+            .unwrap_or(word.len());
+        let (op, version) = word.split_at(op_len);
+        if !matches!(op, "" | "=" | "<" | ">" | "<=" | ">=" | "^" | "~" | "~>") {
+            return None;
+        }
+
+        if version.is_empty() {
+            if pending_op.is_some() {
+                return None;
+            }
+            pending_op = Some(op);
+            continue;
+        }
+
+        let op = match pending_op.take() {
+            Some(pending) if op.is_empty() => pending,
+            Some(_) => return None,
+            None => op,
+        };
+        terms.push(Term::Simple {
+            op: op.to_string(),
+            version: version.to_string(),
+        });
+    }
+
+    if pending_op.is_some() {
+        return None;
+    }
+    Some(terms)
+}
+
+fn parse_partial(version: &str) -> Option<Partial> {
+    let version = version.trim_start_matches(['v', 'V', '=']);
+    let version = version
+        .split_once('+')
+        .map_or(version, |(version, _)| version);
+    let (numbers, pre) = match version.split_once('-') {
+        Some((numbers, pre)) => (numbers, Prerelease::new(pre).ok()?),
+        None => (version, Prerelease::EMPTY),
+    };
+
+    if numbers.is_empty() {
+        return Some(Partial {
+            major: None,
+            minor: None,
+            patch: None,
+            pre,
+        });
+    }
+
+    let mut parts = [None; 3];
+    let mut wildcard = false;
+    for (index, part) in numbers.split('.').enumerate() {
+        if index >= 3 {
+            return None;
+        }
+        if matches!(part, "x" | "X" | "*") {
+            wildcard = true;
+            continue;
+        }
+        let number = part.parse::<u64>().ok()?;
+        // Anything after a wildcard is ignored, so 1.x.3 means 1.x
+        if !wildcard {
+            parts[index] = Some(number);
+        }
+    }
+
+    Some(Partial {
+        major: parts[0],
+        minor: parts[1],
+        patch: parts[2],
+        pre,
+    })
+}
+
+// Function to turn a term into plain comparators, eg. "^1.2.3" into ">=1.2.3 <2.0.0-0".
+// An empty list means the term matches everything
+fn desugar(term: &Term) -> Option<Vec<Comparator>> {
+    match term {
+        Term::Hyphen { from, to } => {
+            let from = parse_partial(from)?;
+            let to = parse_partial(to)?;
+            let mut comparators = desugar_partial(">=", &from);
+            if to.patch.is_some() {
+                comparators.push(comparator(Op::Lte, full(&to)));
+            } else {
+                comparators.extend(desugar_partial("<=", &to));
+            }
+            Some(comparators)
+        }
+        Term::Simple { op, version } => Some(desugar_partial(op, &parse_partial(version)?)),
+    }
+}
+
+fn desugar_partial(op: &str, partial: &Partial) -> Vec<Comparator> {
+    let Some(major) = partial.major else {
+        return match op {
+            "<" | ">" => vec![comparator(Op::Lt, with_pre0(0, 0, 0))],
+            _ => vec![],
+        };
+    };
+    let minor = partial.minor;
+    let patch = partial.patch;
+
+    match (op, minor, patch) {
+        ("" | "=" | "^" | "~" | "~>", None, _) => vec![
+            comparator(Op::Gte, Version::new(major, 0, 0)),
+            comparator(Op::Lt, with_pre0(major + 1, 0, 0)),
+        ],
+        ("" | "=", Some(minor), None) => vec![
+            comparator(Op::Gte, Version::new(major, minor, 0)),
+            comparator(Op::Lt, with_pre0(major, minor + 1, 0)),
+        ],
+        ("" | "=", Some(_), Some(_)) => vec![comparator(Op::Eq, full(partial))],
+        ("^", Some(minor), patch) => {
+            let lower = match patch {
+                Some(_) => full(partial),
+                None => Version::new(major, minor, 0),
+            };
+            let upper = if major > 0 {
+                with_pre0(major + 1, 0, 0)
+            } else if minor > 0 || patch.is_none() {
+                with_pre0(0, minor + 1, 0)
+            } else {
+                with_pre0(0, 0, patch.unwrap_or(0) + 1)
+            };
+            vec![comparator(Op::Gte, lower), comparator(Op::Lt, upper)]
+        }
+        ("~" | "~>", Some(minor), patch) => {
+            let lower = match patch {
+                Some(_) => full(partial),
+                None => Version::new(major, minor, 0),
+            };
+            vec![
+                comparator(Op::Gte, lower),
+                comparator(Op::Lt, with_pre0(major, minor + 1, 0)),
+            ]
+        }
+        (">", None, _) => vec![comparator(Op::Gte, Version::new(major + 1, 0, 0))],
+        (">", Some(minor), None) => vec![comparator(Op::Gte, Version::new(major, minor + 1, 0))],
+        (">", _, _) => vec![comparator(Op::Gt, full(partial))],
+        (">=", _, _) => vec![comparator(
+            Op::Gte,
+            match patch {
+                Some(_) => full(partial),
+                None => Version::new(major, minor.unwrap_or(0), 0),
+            },
+        )],
+        ("<", None, _) => vec![comparator(Op::Lt, with_pre0(major, 0, 0))],
+        ("<", Some(minor), None) => vec![comparator(Op::Lt, with_pre0(major, minor, 0))],
+        ("<", _, _) => vec![comparator(Op::Lt, full(partial))],
+        ("<=", None, _) => vec![comparator(Op::Lt, with_pre0(major + 1, 0, 0))],
+        ("<=", Some(minor), None) => vec![comparator(Op::Lt, with_pre0(major, minor + 1, 0))],
+        _ => vec![comparator(Op::Lte, full(partial))],
+    }
+}
+
+fn comparator(op: Op, version: Version) -> Comparator {
+    Comparator { op, version }
+}
+
+fn full(partial: &Partial) -> Version {
+    Version {
+        major: partial.major.unwrap_or(0),
+        minor: partial.minor.unwrap_or(0),
+        patch: partial.patch.unwrap_or(0),
+        pre: partial.pre.clone(),
+        build: BuildMetadata::EMPTY,
+    }
+}
+
+// The lowest possible prerelease of a version, used for exclusive upper bounds
+// so that <2.0.0-0 also excludes 2.0.0-alpha
+fn with_pre0(major: u64, minor: u64, patch: u64) -> Version {
+    Version {
+        major,
+        minor,
+        patch,
+        pre: Prerelease::new("0").unwrap(),
+        build: BuildMetadata::EMPTY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(spec: &str) -> Range {
+        Range::parse(spec).unwrap_or_else(|| panic!("{} should parse", spec))
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn desugars_partial_and_sugared_ranges() {
+        let cases = [
+            ("^0.0.3", ">=0.0.3 <0.0.4-0"),
+            ("^0.0.x", ">=0.0.0 <0.1.0-0"),
+            ("^0.0", ">=0.0.0 <0.1.0-0"),
+            ("^0.2.3", ">=0.2.3 <0.3.0-0"),
+            ("~1", ">=1.0.0 <2.0.0-0"),
+            ("~1.2", ">=1.2.0 <1.3.0-0"),
+            ("1.x", ">=1.0.0 <2.0.0-0"),
+            ("1.2.x", ">=1.2.0 <1.3.0-0"),
+            ("<=1.2", "<1.3.0-0"),
+            ("<=1", "<2.0.0-0"),
+            (">1", ">=2.0.0"),
+            (">1.2", ">=1.3.0"),
+            ("1.2 - 2.3", ">=1.2.0 <2.4.0-0"),
+            ("1.2.3 - 2", ">=1.2.3 <3.0.0-0"),
+            ("1 - 2.3.4", ">=1.0.0 <=2.3.4"),
+            (">= 1.2.3", ">=1.2.3"),
+        ];
+        for (sugared, plain) in cases {
+            assert_eq!(range(sugared), range(plain), "{}", sugared);
+        }
+    }
+
+    #[test]
+    fn matches_everything_for_wildcards() {
+        for spec in ["*", "x", "", "X"] {
+            assert!(range(spec).is_any(), "{}", spec);
+        }
+        assert!(!range("^1.0.0").is_any());
+        assert!(Range::parse(">>1.0.0").is_none());
+        assert!(Range::parse("1.2.3.4").is_none());
+    }
+
+    #[test]
+    fn only_matches_prereleases_of_the_same_version() {
+        let beta = range("^1.2.3-beta.1");
+        assert!(beta.satisfies(&version("1.2.3-beta.2")));
+        assert!(beta.satisfies(&version("1.3.0")));
+        assert!(!beta.satisfies(&version("1.2.3-alpha.9")));
+        assert!(!beta.satisfies(&version("1.2.4-beta.1")));
+
+        let stable = range("^1.2.3");
+        assert!(!stable.satisfies(&version("1.3.0-beta.1")));
+        assert!(stable.satisfies_including_prerelease(&version("1.3.0-beta.1")));
+        assert!(!range("<2.0.0").satisfies(&version("2.0.0-rc.1")));
+        assert!(!range("^1.0.0").satisfies_including_prerelease(&version("2.0.0-rc.1")));
+    }
+
+    #[test]
+    fn finds_the_floor_of_alternatives() {
+        let alternatives = range("^2.0.0 || ^1.2.0");
+        assert_eq!(alternatives.min_version(), Some(version("1.2.0")));
+        assert_eq!(alternatives.highest_floor(), Some(version("2.0.0")));
+
+        assert_eq!(range(">1.2.3 || >=5").min_version(), Some(version("1.2.4")));
+        assert_eq!(
+            range(">=1.0.0-rc.1 || 2").min_version(),
+            Some(version("1.0.0-rc.1"))
+        );
+        assert_eq!(range("<1.0.0").min_version(), Some(version("0.0.0")));
+        assert_eq!(range("*").min_version(), Some(version("0.0.0")));
+    }
+
+    #[test]
+    fn rewrites_compound_ranges() {
+        let cases = [
+            ("^18.2.0 || ^17.0.0", "18.3.1", "^18.3.1 || ^17.0.0"),
+            ("^17.0.0 || ^18.2.0", "18.3.1", "^17.0.0 || ^18.3.1"),
+            (
+                ">=1.0.0 <2.0.0 || ^3.0.0",
+                "3.1.0",
+                ">=1.0.0 <2.0.0 || ^3.1.0",
+            ),
+            (">=1.2.0 <2.0.0", "2.1.0", ">=2.1.0 <3.0.0"),
+            (">=1.2.0 <2.0.0", "1.4.0", ">=1.4.0 <2.0.0"),
+            (">=1.2.0 <=1.5.0", "1.6.0", ">=1.6.0 <=1.6.0"),
+            ("1.2.3 - 1.4.0", "1.5.0", "1.2.3 - 1.5.0"),
+            ("1.2.3 - 1.4.0", "1.3.0", "1.2.3 - 1.4.0"),
+            ("<2.0.0", "1.5.0", "<2.0.0"),
+            ("<2.0.0", "3.0.0", "<4.0.0"),
+            ("<=1.5.0", "1.3.0", "<=1.5.0"),
+            ("<=1.5.0", "1.6.0", "<=1.6.0"),
+            ("1.x", "2.3.4", "2.x"),
+            ("1.2.x", "1.3.0", "1.3.x"),
+            ("~1.2", "1.3.0", "~1.3"),
+            (">1.0.0", "2.0.0", ">=2.0.0"),
+            ("*", "2.0.0", "2.0.0"),
+        ];
+        for (spec, new_version, expected) in cases {
+            assert_eq!(
+                rewrite(spec, &version(new_version)).as_deref(),
+                Some(expected),
+                "{} to {}",
+                spec,
+                new_version
+            );
+        }
+        assert_eq!(rewrite("not a range", &version("1.0.0")), None);
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::constants::{
+    DEPENDENCIES, DEV_DEPENDENCIES, EXIT_FETCH_ERROR, EXIT_OUTDATED, EXIT_UP_TO_DATE,
+    PEER_DEPENDENCIES,
+};
+
+// Which section of package.json a dependency is declared in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyType {
+    Prod,
+    Dev,
+    Peer,
+    // A version in a pnpm or bun catalog
+    Catalog,
+}
+
+impl DependencyType {
+    // The package.json key of the section, eg. "devDependencies"
+    pub fn key(&self) -> &'static str {
+        match self {
+            DependencyType::Prod => DEPENDENCIES,
+            DependencyType::Dev => DEV_DEPENDENCIES,
+            DependencyType::Peer => PEER_DEPENDENCIES,
+            DependencyType::Catalog => "catalog",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DependencyType::Prod => "prod",
+            DependencyType::Dev => "dev",
+            DependencyType::Peer => "peer",
+            DependencyType::Catalog => "catalog",
+        }
+    }
+}
+
+// How big of a jump an update is, from the smallest to the biggest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bump {
+    // A newer build of the same prerelease, eg. 2.0.0-rc.3 to 2.0.0-rc.4
+    Prerelease,
+    Patch,
+    Minor,
+    Major,
+}
+
+// Everything nrush found out about one dependency
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyResult {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub dependency_type: DependencyType,
+    // The spec from package.json, eg. "^1.2.3"
+    pub current: String,
+    // The version the lockfile says is installed
+    pub installed: Option<String>,
+    // The version the target resolved to, even if it's not an update
+    pub target: Option<String>,
+    // Only set if the target is an update for the current spec
+    pub bump: Option<Bump>,
+    pub updated: bool,
+    // A newer version the cooldown kept back, and how old it is
+    pub held_back: Option<String>,
+    #[serde(skip)]
+    pub held_back_age: Option<Duration>,
+    // Deprecation message of the current version
+    pub deprecated: Option<String>,
+    // Why the dependency wasn't looked up, eg. "local path"
+    pub skipped: Option<&'static str>,
+    pub error: Option<String>,
+    // The spec every workspace member is aligned to, only set with --consistent
+    // when the members declare the dependency with different ranges
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aligned: Option<String>,
+    // Name of the workspace member or catalog declaring it, eg. "catalog:react17".
+    // Members are only named with --workspaces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    // Index of the manifest it came from
+    #[serde(skip)]
+    pub manifest: usize,
+    // Keys of the object it's declared in, eg. ["devDependencies"]
+    #[serde(skip)]
+    pub section: Vec<String>,
+}
+
+impl DependencyResult {
+    pub fn new(name: &str, dependency_type: DependencyType, current: &str) -> DependencyResult {
+        DependencyResult {
+            name: name.to_string(),
+            dependency_type,
+            current: current.to_string(),
+            installed: None,
+            target: None,
+            bump: None,
+            updated: false,
+            held_back: None,
+            held_back_age: None,
+            deprecated: None,
+            skipped: None,
+            error: None,
+            aligned: None,
+            workspace: None,
+            manifest: 0,
+            section: vec![dependency_type.key().to_string()],
+        }
+    }
+
+    pub fn is_outdated(&self) -> bool {
+        self.bump.is_some() || self.is_misaligned()
+    }
+
+    // Whether --consistent wants a different spec than the one declared
+    pub fn is_misaligned(&self) -> bool {
+        self.aligned
+            .as_ref()
+            .is_some_and(|aligned| *aligned != self.current)
+    }
+}
+
+// The report printed with --format json
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub manifest: String,
+    pub target: &'static str,
+    pub dependencies: &'a [DependencyResult],
+}
+
+// Function to get the exit code of --check. Failed lookups win, since nrush can't tell
+// whether those are up to date, then updates at least as big as --error-level and
+// ranges --consistent would align
+pub fn check_exit_code(results: &[DependencyResult], error_level: Option<Bump>) -> i32 {
+    if results.iter().any(|result| result.error.is_some()) {
+        return EXIT_FETCH_ERROR;
+    }
+
+    let outdated = results.iter().any(|result| {
+        result.is_misaligned()
+            || result
+                .bump
+                .is_some_and(|bump| error_level.is_none_or(|level| bump >= level))
+    });
+    if outdated {
+        EXIT_OUTDATED
+    } else {
+        EXIT_UP_TO_DATE
+    }
+}
@@ -0,0 +1,98 @@
+use crate::range::Range;
+
+// What a dependency value in package.json points to
+#[derive(Debug, PartialEq)]
+pub enum Specifier<'a> {
+    // A semver range resolved against the registry, eg. "^1.2.3"
+    Range(&'a str),
+    // An npm: alias to another registry package, eg. "npm:other-pkg@^2"
+    Alias { name: &'a str, range: &'a str },
+    // Anything nrush can't upgrade, with the reason why
+    Skip(&'static str),
+}
+
+// Protocols used by npm, yarn, pnpm and bun that don't resolve against the registry
+const PROTOCOLS: &[(&str, &str)] = &[
+    ("workspace:", "workspace package"),
+    ("catalog:", "catalog version"),
+    ("file:", "local file"),
+    ("link:", "linked package"),
+    ("portal:", "portal package"),
+    ("patch:", "patched package"),
+    ("exec:", "exec script"),
+    ("jsr:", "JSR package"),
+    ("git:", "git repository"),
+    ("git+ssh:", "git repository"),
+    ("git+http:", "git repository"),
+    ("git+https:", "git repository"),
+    ("git+file:", "git repository"),
+    ("ssh:", "git repository"),
+    ("github:", "git repository"),
+    ("gitlab:", "git repository"),
+    ("bitbucket:", "git repository"),
+    ("gist:", "git repository"),
+    ("http:", "tarball URL"),
+    ("https:", "tarball URL"),
+];
+
+// Function to classify a dependency specifier from package.json
+pub fn classify(spec: &str) -> Specifier<'_> {
+    let spec = spec.trim();
+
+    if let Some(alias) = spec.strip_prefix("npm:").filter(|alias| !alias.is_empty()) {
+        // The version separator is the last @, except for the one starting a scope
+        let (name, range) = match alias.rsplit_once('@') {
+            Some((name, range)) if !name.is_empty() => (name, range),
+            _ => (alias, "*"),
+        };
+        let range = if range.is_empty() { "*" } else { range };
+        return match Range::parse(range) {
+            Some(_) => Specifier::Alias { name, range },
+            None => Specifier::Skip("alias to a dist-tag"),
+        };
+    }
+
+    let lowercase = spec.to_lowercase();
+    if let Some((_, reason)) = PROTOCOLS
+        .iter()
+        .find(|(protocol, _)| lowercase.starts_with(protocol))
+    {
+        return Specifier::Skip(reason);
+    }
+
+    if ["./", "../", "/", "~/", ".\\", "..\\"]
+        .iter()
+        .any(|prefix| spec.starts_with(prefix))
+        || [".tgz", ".tar.gz", ".tar"]
+            .iter()
+            .any(|suffix| lowercase.ends_with(suffix))
+    {
+        return Specifier::Skip("local path");
+    }
+
+    // GitHub shorthand, eg. "org/repo#v1"
+    if spec.contains('/') {
+        return Specifier::Skip("git repository");
+    }
+
+    if Range::parse(spec).is_some() {
+        return Specifier::Range(spec);
+    }
+
+    if spec
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        Specifier::Skip("dist-tag")
+    } else {
+        Specifier::Skip("unrecognized specifier")
+    }
+}
+
+// Function to get the semver range part of a specifier, if it has one
+pub fn version_range(spec: &str) -> Option<&str> {
+    match classify(spec) {
+        Specifier::Range(range) | Specifier::Alias { range, .. } => Some(range),
+        Specifier::Skip(_) => None,
+    }
+}
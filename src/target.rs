@@ -0,0 +1,402 @@
+use std::time::Duration;
+
+use clap::ValueEnum;
+use semver::Version;
+
+use crate::packages::{floor_version, Packument};
+use crate::range::Range;
+use crate::specifier::version_range;
+
+// Which version to upgrade to, same names as npm-check-updates uses
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Target {
+    /// The version the dist-tag points to
+    Latest,
+    /// The highest version number
+    Greatest,
+    /// The most recently published version
+    Newest,
+    /// The highest version that keeps the current major
+    Minor,
+    /// The highest version that keeps the current major.minor
+    Patch,
+    /// The highest version the current range allows
+    Semver,
+}
+
+impl Target {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Latest => "latest",
+            Target::Greatest => "greatest",
+            Target::Newest => "newest",
+            Target::Minor => "minor",
+            Target::Patch => "patch",
+            Target::Semver => "semver",
+        }
+    }
+
+    // Whether the strategy needs publish times, which only the full packument has
+    pub fn needs_time(&self) -> bool {
+        *self == Target::Newest
+    }
+
+    pub fn strategy(&self) -> &'static dyn Strategy {
+        match self {
+            Target::Latest => &Latest,
+            Target::Greatest => &Greatest,
+            Target::Newest => &Newest,
+            Target::Minor => &Minor,
+            Target::Patch => &Patch,
+            Target::Semver => &Semver,
+        }
+    }
+}
+
+// Everything a strategy can look at to pick a version
+pub struct Candidates<'a> {
+    pub packument: &'a Packument,
+    // Every published version that is valid semver and may be offered. Prereleases are
+    // only in here with --pre, or if they're newer builds of the prerelease in use
+    pub versions: Vec<Version>,
+    // The spec from package.json, eg. "^1.2.3"
+    pub current: &'a str,
+    // Dist-tag the latest target reads, eg. "next"
+    pub tag: &'a str,
+    // Whether --pre was passed, so stable versions can move to prereleases
+    pub pre: bool,
+    // How old a version has to be to be offered, from --cooldown
+    pub cooldown: Option<Duration>,
+}
+
+impl Candidates<'_> {
+    // Whether a version can be offered: it isn't deprecated and it's old enough for the
+    // cooldown. Versions without a publish time never are, since there's no telling how new they are
+    pub fn is_allowed(&self, version: &Version) -> bool {
+        let version = version.to_string();
+        self.packument.deprecation(&version).is_none()
+            && self.cooldown.is_none_or(|cooldown| {
+                self.packument
+                    .age(&version)
+                    .is_some_and(|age| age >= cooldown)
+            })
+    }
+}
+
+pub trait Strategy {
+    fn select(&self, candidates: &Candidates) -> Option<Version>;
+}
+
+// The version the dist-tag points to, falling back to latest if the package doesn't have it.
+// With --pre, the highest version any dist-tag points to, eg. the one tagged next or beta.
+// If that's deprecated or too new for the cooldown, the highest allowed version below it
+struct Latest;
+
+impl Strategy for Latest {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        let dist_tags = &candidates.packument.dist_tags;
+        let version = dist_tags
+            .get(candidates.tag)
+            .or_else(|| dist_tags.get("latest"))?;
+        let mut version = Version::parse(version).ok()?;
+        if candidates.pre {
+            version = dist_tags
+                .values()
+                .filter_map(|version| Version::parse(version).ok())
+                .chain([version])
+                .max()?;
+        }
+
+        if candidates.is_allowed(&version) {
+            return Some(version);
+        }
+        candidates
+            .versions
+            .iter()
+            .filter(|candidate| **candidate < version)
+            .max()
+            .cloned()
+    }
+}
+
+// The highest version number, regardless of tags and publish dates
+struct Greatest;
+
+impl Strategy for Greatest {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        max_in(candidates, "*")
+    }
+}
+
+// The most recently published version, even if there are higher version numbers
+struct Newest;
+
+impl Strategy for Newest {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        let time = &candidates.packument.time;
+        candidates
+            .versions
+            .iter()
+            .filter_map(|version| Some((time.get(&version.to_string())?, version)))
+            .max_by(|a, b| a.0.cmp(b.0)) // ISO 8601 timestamps sort as strings
+            .map(|(_, version)| version.clone())
+    }
+}
+
+// The highest version that keeps the current major, or latest if there's no version to go by.
+// For "a || b" the major is the one of the alternative an update rewrites
+struct Minor;
+
+impl Strategy for Minor {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        let Some(current) = floor_version(candidates.current) else {
+            return Latest.select(candidates);
+        };
+        max_in(candidates, &format!("{}.x", current.major))
+    }
+}
+
+// The highest version that keeps the current major.minor, or latest if there's no version
+// to go by. For "a || b" it's the one of the alternative an update rewrites
+struct Patch;
+
+impl Strategy for Patch {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        let Some(current) = floor_version(candidates.current) else {
+            return Latest.select(candidates);
+        };
+        max_in(
+            candidates,
+            &format!("{}.{}.x", current.major, current.minor),
+        )
+    }
+}
+
+// The highest version the current range already allows
+struct Semver;
+
+impl Strategy for Semver {
+    fn select(&self, candidates: &Candidates) -> Option<Version> {
+        max_in(candidates, version_range(candidates.current)?)
+    }
+}
+
+// Function to find the highest candidate in a range. The candidates are already limited
+// to the prereleases that may be offered, so those only have to match the comparators
+fn max_in(candidates: &Candidates, range: &str) -> Option<Version> {
+    let range = Range::parse(range)?;
+    candidates
+        .versions
+        .iter()
+        .filter(|version| range.satisfies_including_prerelease(version))
+        .max()
+        .cloned()
+}
+
+// The version to offer for a package, and the newer one the cooldown held back
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub version: Option<String>,
+    pub held_back: Option<String>,
+}
+
+// Function to pick the version to offer for a package with the given target.
+// Prereleases are only offered with --pre, or when the current version is a prerelease
+// and there's a newer build of it, eg. 2.0.0-rc.3 to 2.0.0-rc.4.
+// A prerelease is never downgraded to an older release, eg. 2.0.0-rc.3 to 1.9.0.
+// Deprecated versions are never offered.
+// With a cooldown, versions published too recently are left out, and the version the
+// target would have picked without it is reported as held back
+pub fn select_version(
+    packument: &Packument,
+    current: &str,
+    target: Target,
+    tag: &str,
+    pre: bool,
+    cooldown: Option<Duration>,
+) -> Selection {
+    let newest = pick(packument, current, target, tag, pre, None);
+    if cooldown.is_none() {
+        return Selection {
+            version: newest.map(|version| version.to_string()),
+            held_back: None,
+        };
+    }
+
+    let version = pick(packument, current, target, tag, pre, cooldown);
+    let held_back = newest.filter(|newest| version.as_ref().is_none_or(|version| newest > version));
+    Selection {
+        version: version.map(|version| version.to_string()),
+        held_back: held_back.map(|version| version.to_string()),
+    }
+}
+
+fn pick(
+    packument: &Packument,
+    current: &str,
+    target: Target,
+    tag: &str,
+    pre: bool,
+    cooldown: Option<Duration>,
+) -> Option<Version> {
+    let floor = floor_version(current).filter(|floor| !floor.pre.is_empty());
+    let is_sibling = |version: &Version| {
+        floor.as_ref().is_some_and(|floor| {
+            (floor.major, floor.minor, floor.patch) == (version.major, version.minor, version.patch)
+        })
+    };
+
+    let mut candidates = Candidates {
+        packument,
+        versions: vec![],
+        current,
+        tag,
+        pre,
+        cooldown,
+    };
+    candidates.versions = packument
+        .parsed_versions()
+        .into_iter()
+        .filter(|version| pre || version.pre.is_empty() || is_sibling(version))
+        .filter(|version| candidates.is_allowed(version))
+        .collect();
+
+    let version = target.strategy().select(&candidates);
+    let Some(floor) = &floor else {
+        return version;
+    };
+    version.filter(|version| version > floor).or_else(|| {
+        candidates
+            .versions
+            .iter()
+            .filter(|version| is_sibling(version) && *version > floor)
+            .max()
+            .cloned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DAY: u64 = 86_400;
+
+    fn packument() -> Packument {
+        serde_json::from_value(json!({
+            "dist-tags": {"latest": "1.9.0", "next": "2.0.0-rc.4"},
+            "versions": {
+                "1.0.0": {},
+                "1.8.0": {},
+                "1.9.0": {},
+                "2.0.0-rc.3": {},
+                "2.0.0-rc.4": {}
+            },
+            "time": {
+                "1.0.0": "2020-01-01T00:00:00.000Z",
+                "1.8.0": "2020-06-01T00:00:00.000Z",
+                "1.9.0": "2999-01-01T00:00:00.000Z",
+                "2.0.0-rc.3": "2020-07-01T00:00:00.000Z",
+                "2.0.0-rc.4": "2999-01-01T00:00:00.000Z"
+            }
+        }))
+        .unwrap()
+    }
+
+    fn select(packument: &Packument, current: &str, tag: &str, pre: bool) -> Option<String> {
+        select_version(packument, current, Target::Latest, tag, pre, None).version
+    }
+
+    #[test]
+    fn moves_to_newer_builds_of_a_prerelease() {
+        let packument = packument();
+        assert_eq!(
+            select(&packument, "^2.0.0-rc.3", "latest", false).as_deref(),
+            Some("2.0.0-rc.4")
+        );
+        // Stable versions only move to prereleases with --pre
+        assert_eq!(
+            select(&packument, "^1.0.0", "latest", false).as_deref(),
+            Some("1.9.0")
+        );
+        assert_eq!(
+            select(&packument, "^1.0.0", "latest", true).as_deref(),
+            Some("2.0.0-rc.4")
+        );
+    }
+
+    #[test]
+    fn never_downgrades_a_prerelease() {
+        let packument = packument();
+        assert_eq!(select(&packument, "2.0.0-rc.4", "latest", false), None);
+        let selection = select_version(
+            &packument,
+            "2.0.0-rc.4",
+            Target::Greatest,
+            "latest",
+            false,
+            None,
+        );
+        assert_eq!(selection.version, None);
+    }
+
+    #[test]
+    fn falls_back_from_a_deprecated_latest() {
+        let packument: Packument = serde_json::from_value(json!({
+            "dist-tags": {"latest": "1.9.0"},
+            "versions": {
+                "1.0.0": {},
+                "1.8.0": {"deprecated": ""},
+                "1.8.5": {},
+                "1.9.0": {"deprecated": "Broken build, use 1.8.5"}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            select(&packument, "^1.0.0", "latest", false).as_deref(),
+            Some("1.8.5")
+        );
+        let selection = select_version(&packument, "^1.0.0", Target::Minor, "latest", false, None);
+        assert_eq!(selection.version.as_deref(), Some("1.8.5"));
+    }
+
+    #[test]
+    fn holds_back_versions_newer_than_the_cooldown() {
+        let packument = packument();
+        let selection = select_version(
+            &packument,
+            "^1.0.0",
+            Target::Latest,
+            "latest",
+            false,
+            Some(Duration::from_secs(7 * DAY)),
+        );
+        assert_eq!(selection.version.as_deref(), Some("1.8.0"));
+        assert_eq!(selection.held_back.as_deref(), Some("1.9.0"));
+
+        // Nothing is held back when the pick is old enough
+        let selection = select_version(
+            &packument,
+            "^1.0.0",
+            Target::Patch,
+            "latest",
+            false,
+            Some(Duration::from_secs(7 * DAY)),
+        );
+        assert_eq!(selection.version.as_deref(), Some("1.0.0"));
+        assert_eq!(selection.held_back, None);
+    }
+
+    #[test]
+    fn falls_back_to_latest_for_a_missing_tag() {
+        let packument = packument();
+        assert_eq!(
+            select(&packument, "^1.0.0", "beta", false).as_deref(),
+            Some("1.9.0")
+        );
+        assert_eq!(
+            select(&packument, "^2.0.0-rc.3", "next", false).as_deref(),
+            Some("2.0.0-rc.4")
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde_json::Value;
+
+use crate::arguments::RangePolicy;
+use crate::manifest::MANIFEST_FILE;
+use crate::packages::{bump_kind, floor_version, new_version_spec};
+use crate::report::{DependencyResult, DependencyType};
+
+pub const PNPM_WORKSPACE_FILE: &str = "pnpm-workspace.yaml";
+
+// Function to find the package.json of every workspace member under a root.
+// Patterns come from the root's `workspaces` field (npm, yarn and bun, either an array
+// or an object with `packages`) and from pnpm-workspace.yaml. Patterns starting with
+// ! exclude members, eg. "!packages/internal"
+pub fn discover_members(root_dir: &Path, root_json: &Value) -> Vec<PathBuf> {
+    let mut patterns = workspace_patterns(root_json);
+    if let Ok(content) = fs::read_to_string(root_dir.join(PNPM_WORKSPACE_FILE)) {
+        patterns.extend(read_pnpm_packages(&content));
+    }
+
+    let (excludes, includes): (Vec<_>, Vec<_>) = patterns
+        .iter()
+        .map(|pattern| pattern.trim().trim_start_matches("./"))
+        .partition(|pattern| pattern.starts_with('!'));
+    let excludes = excludes
+        .iter()
+        .map(|pattern| pattern[1..].trim_start_matches("./"))
+        .collect::<Vec<_>>();
+
+    let mut members = vec![];
+    for pattern in includes {
+        let segments = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+        expand(root_dir, &segments, &mut members);
+    }
+
+    members.retain(|dir| {
+        let relative = relative_path(root_dir, dir);
+        !relative.is_empty()
+            && !excludes
+                .iter()
+                .any(|pattern| glob_match(pattern, &relative))
+    });
+    members.sort();
+    members.dedup();
+    members
+        .into_iter()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .filter(|manifest| manifest.is_file())
+        .collect()
+}
+
+// Function to find the root of the workspace a project is a member of, which is where
+// the lockfile and the packageManager field live. It's the nearest parent directory
+// whose workspace patterns include the project, or the project itself when there's none.
+// The returned path is canonical so paths under it can be made relative to it
+pub fn workspace_root(project_dir: &Path) -> PathBuf {
+    let project_dir = project_dir
+        .canonicalize()
+        .unwrap_or_else(|_| project_dir.to_path_buf());
+    let manifest = project_dir.join(MANIFEST_FILE);
+
+    for dir in project_dir.ancestors().skip(1) {
+        let json = fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .unwrap_or_default();
+        if json["workspaces"].is_null() && !dir.join(PNPM_WORKSPACE_FILE).is_file() {
+            continue;
+        }
+        if discover_members(dir, &json).contains(&manifest) {
+            return dir.to_path_buf();
+        }
+    }
+    project_dir
+}
+
+// Function to read the patterns of the `workspaces` field in package.json
+fn workspace_patterns(root_json: &Value) -> Vec<String> {
+    let workspaces = match &root_json["workspaces"] {
+        Value::Object(workspaces) => workspaces.get("packages").unwrap_or(&Value::Null),
+        workspaces => workspaces,
+    };
+    workspaces
+        .as_array()
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(|pattern| pattern.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Function to read the `packages` list of pnpm-workspace.yaml. Only the YAML pnpm
+// writes is understood: a block list under `packages:` or an inline [a, b] list
+pub fn read_pnpm_packages(content: &str) -> Vec<String> {
+    let mut patterns = vec![];
+    let mut in_packages = false;
+
+    for line in content.lines() {
+        let line = strip_comment(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = false;
+            if let Some(value) = line.strip_prefix("packages:") {
+                let value = value.trim();
+                if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                    patterns.extend(list.split(',').map(unquote).filter(|p| !p.is_empty()));
+                } else {
+                    in_packages = value.is_empty();
+                }
+            }
+            continue;
+        }
+
+        if in_packages {
+            if let Some(pattern) = line.trim().strip_prefix('-') {
+                patterns.push(unquote(pattern));
+            }
+        }
+    }
+    patterns
+}
+
+// Function to drop a # comment, as long as it isn't inside quotes
+pub fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (index, c) in line.char_indices() {
+        match (c, quote) {
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) if index == 0 || line[..index].ends_with([' ', '\t']) => {
+                return line[..index].trim_end();
+            }
+            _ => (),
+        }
+    }
+    line.trim_end()
+}
+
+pub fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+        .or_else(|| {
+            value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+        })
+        .unwrap_or(value)
+        .to_string()
+}
+
+// Function to split a `key: value` line, returning the unquoted key and where the value
+// is in the line. Keys can be quoted, eg. '@scope/pkg': ^1.0.0
+pub fn split_key(line: &str) -> Option<(String, Range<usize>)> {
+    let colon = match line.chars().next()? {
+        quote @ ('\'' | '"') => line[1..].find(quote)? + 2,
+        _ => line.find(':')?,
+    };
+    if line.as_bytes().get(colon) != Some(&b':') {
+        return None;
+    }
+    let key = unquote(&line[..colon]);
+    let rest = &line[colon + 1..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let value_start = colon + 1 + (rest.len() - rest.trim_start().len());
+    Some((key, value_start..line.len()))
+}
+
+// Function to walk the directories matching the pattern segments, where ** matches any
+// number of directories. node_modules and hidden directories are never walked into
+fn expand(dir: &Path, segments: &[&str], found: &mut Vec<PathBuf>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        found.push(dir.to_path_buf());
+        return;
+    };
+
+    if *segment == "**" {
+        expand(dir, rest, found);
+        for child in child_dirs(dir) {
+            expand(&child, segments, found);
+        }
+        return;
+    }
+
+    if !segment.contains(['*', '?']) {
+        let child = dir.join(segment);
+        if child.is_dir() {
+            expand(&child, rest, found);
+        }
+        return;
+    }
+
+    for child in child_dirs(dir) {
+        let name = child
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if glob_match(segment, &name) {
+            expand(&child, rest, found);
+        }
+    }
+}
+
+fn child_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut dirs = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name != "node_modules" && !name.starts_with('.')
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+// Function to match a path against a glob, where * and ? stay within one directory
+// and ** matches any number of them
+fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+                let rest = rest[1..].strip_prefix(b"/").unwrap_or(&rest[1..]);
+                rest.is_empty()
+                    || (0..=path.len()).any(|start| {
+                        (start == 0 || path[start - 1] == b'/') && matches(rest, &path[start..])
+                    })
+            }
+            Some((b'*', rest)) => (0..=path.len())
+                .take_while(|end| !path[..*end].contains(&b'/'))
+                .any(|end| matches(rest, &path[end..])),
+            // A trailing /** also matches the directory itself, eg. "test" for "test/**"
+            Some((b'/', rest)) if rest == b"**" && path.is_empty() => true,
+            Some((b'?', rest)) => {
+                path.first().is_some_and(|c| *c != b'/') && matches(rest, &path[1..])
+            }
+            Some((c, rest)) => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+    matches(pattern.trim_end_matches('/').as_bytes(), path.as_bytes())
+}
+
+// Function to get a path relative to the root with forward slashes, eg. "packages/ui"
+pub fn relative_path(root_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(root_dir)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// A dependency declared with different ranges across workspace members
+pub struct Mismatch {
+    pub name: String,
+    // Whether the declarations were aligned, which doesn't happen when that would go
+    // past the version the target allows for one of them
+    pub aligned: bool,
+}
+
+// Whether --consistent looks at a dependency. Peer ranges are meant to be wide and
+// catalogs can differ on purpose, so both are left alone
+pub fn is_alignable(result: &DependencyResult) -> bool {
+    result.error.is_none()
+        && !matches!(
+            result.dependency_type,
+            DependencyType::Peer | DependencyType::Catalog
+        )
+}
+
+// Function to find dependencies declared with different ranges across workspace members
+// and align every declaration to one spec: the newest version any of them resolved to,
+// written like the highest declared range (the first one on a tie). The version can't
+// be newer than what the target picked for any of them, eg. with --target minor a member
+// on ^17.0.2 isn't moved to ^18.3.1, and the mismatch is only reported then
+pub fn align_ranges(results: &mut [DependencyResult], policy: RangePolicy) -> Vec<Mismatch> {
+    let mut names: Vec<String> = vec![];
+    for result in results.iter().filter(|result| is_alignable(result)) {
+        if !names.contains(&result.name) {
+            names.push(result.name.clone());
+        }
+    }
+
+    let mut mismatched = vec![];
+    for name in names {
+        let mut group = results
+            .iter_mut()
+            .filter(|result| is_alignable(result) && result.name == name)
+            .collect::<Vec<_>>();
+        if group
+            .iter()
+            .all(|result| result.current == group[0].current)
+        {
+            continue;
+        }
+
+        let Some(highest) = group
+            .iter()
+            .rev()
+            .max_by_key(|result| floor_version(&result.current))
+            .map(|result| result.current.clone())
+        else {
+            continue;
+        };
+        let Some(version) = group
+            .iter()
+            .filter_map(|result| result.target.as_deref())
+            .filter_map(|target| Version::parse(target).ok())
+            .chain(floor_version(&highest))
+            .max()
+        else {
+            continue;
+        };
+        let allowed = group.iter().all(|result| {
+            result
+                .target
+                .as_deref()
+                .and_then(|target| Version::parse(target).ok())
+                .or_else(|| floor_version(&result.current))
+                .is_some_and(|limit| version <= limit)
+        });
+        if !allowed {
+            mismatched.push(Mismatch {
+                name,
+                aligned: false,
+            });
+            continue;
+        }
+
+        let version = version.to_string();
+        let aligned = new_version_spec(&highest, &version, policy);
+
+        for result in group.iter_mut() {
+            result.bump = bump_kind(&result.current, &version);
+            result.target = Some(version.clone());
+            result.aligned = Some(aligned.clone());
+        }
+        mismatched.push(Mismatch {
+            name,
+            aligned: true,
+        });
+    }
+    mismatched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+
+    #[test]
+    fn matches_globs() {
+        let cases = [
+            ("packages/*", "packages/ui", true),
+            ("packages/*", "packages/ui/nested", false),
+            ("packages/*", "packages", false),
+            ("packages/*/", "packages/ui", true),
+            ("packages/ui-?", "packages/ui-a", true),
+            ("packages/ui-?", "packages/ui-ab", false),
+            ("packages/?", "packages/a/b", false),
+            ("apps/**", "apps/web/admin", true),
+            ("apps/**", "apps", true),
+            ("**/test/**", "packages/test", true),
+            ("**/test/**", "packages/test/fixtures", true),
+            ("**/test/**", "test", true),
+            ("**/test/**", "packages/testing", false),
+            ("**/internal", "packages/tools/internal", true),
+            ("**/internal", "packages/internal-ui", false),
+        ];
+        for (pattern, path, expected) in cases {
+            assert_eq!(
+                glob_match(pattern, path),
+                expected,
+                "{} on {}",
+                pattern,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn reads_block_pnpm_packages() {
+        let content = "# workspace\r\npackages:\r\n  - packages/*\r\n  - 'apps/**' # all apps\r\n  - \"!**/test/**\"\r\n\r\ncatalog:\r\n  react: ^18.2.0\r\n";
+        assert_eq!(
+            read_pnpm_packages(content),
+            ["packages/*", "apps/**", "!**/test/**"]
+        );
+
+        let content =
+            "packages:\n- packages/*\n- tools/#cli\nonlyBuiltDependencies:\n  - esbuild\n";
+        assert_eq!(read_pnpm_packages(content), ["packages/*", "tools/#cli"]);
+    }
+
+    #[test]
+    fn reads_inline_pnpm_packages() {
+        let content = "packages: ['packages/*', \"apps/*\" , '!apps/legacy'] # inline\n";
+        assert_eq!(
+            read_pnpm_packages(content),
+            ["packages/*", "apps/*", "!apps/legacy"]
+        );
+        assert!(read_pnpm_packages("packages: []\n").is_empty());
+    }
+
+    #[test]
+    fn excludes_members() {
+        let root = env::temp_dir().join(format!("nrush-workspace-{}", std::process::id()));
+        for dir in [
+            "packages/ui",
+            "packages/test",
+            "packages/test/fixture",
+            "apps/web",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join(MANIFEST_FILE), "{}").unwrap();
+        }
+        let members = discover_members(
+            &root,
+            &json!({"workspaces": ["packages/**", "apps/*", "!**/test/**"]}),
+        );
+        let members = members
+            .iter()
+            .map(|manifest| relative_path(&root, manifest))
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            members,
+            ["apps/web/package.json", "packages/ui/package.json"]
+        );
+    }
+}